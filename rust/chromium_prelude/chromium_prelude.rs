@@ -2,16 +2,20 @@
 // Use of this source code is governed by a BSD-style license that can be
 // found in the LICENSE file.
 
+// The `chromium` crate is available to `no_std` crates too.
+#![no_std]
+
 /// The `chromium::import!{}` macro for importing crates from GN paths.
 ///
 /// This macro is used to access first-party crates in the Chromium project
 /// (or other projects using Chromium's //build system) through the GN path
-/// to the crate. All GN paths must be absolute paths.
+/// to the crate. GN paths may be absolute (starting with `//`) or relative to
+/// the directory of the crate being compiled, as within the GN `deps` list.
 ///
 /// Third-party crates are accessed as usual by their name, which is available
-/// whenever the Rust target depends on the third-party crate. The `import!`
-/// macro does nothing, and will cause a compilation error if an attempt is
-/// made to import a third-party crate with it.
+/// whenever the Rust target depends on the third-party crate. They can also be
+/// imported by their versioned GN path, which states the epoch of the crate
+/// that is expected. See "Third-party crates" below.
 ///
 /// # Motivation
 ///
@@ -28,6 +32,11 @@
 /// should be given a list of GN paths (directory and target name) as quoted
 /// strings to be imported into the current module, delineated with semicolons.
 ///
+/// Each imported GN target must also be listed in the `deps` (or
+/// `public_deps`) of the crate's GN target. Otherwise the macro reports an
+/// error naming the missing GN path, and suggests the closest one that is
+/// present.
+///
 /// When no target name is specified (e.g. `:name`) at the end of the GN path,
 /// the target will be the same as the last directory name. This is the same
 /// behaviour as within the GN `deps` list.
@@ -39,22 +48,42 @@
 ///
 /// # Examples
 ///
+/// The examples below are run as the doctests of the `chromium` crate, which
+/// is defined in `build/rust/chromium_prelude/BUILD.gn`, and import the crates
+/// in `build/rust/chromium_prelude/example/BUILD.gn`.
+///
 /// ## Basic usage
 /// Basic usage, importing a few targets. The name given to the imported crates
 /// is their GN target name by default. In this example, there would be two
 /// crates available in the Rust module below: `example` which is the
-/// `example` GN target in `rust/example/BUILD.gn` and `other` which is the
-/// `other` GN target in `rust/example/BUILD.gn`.
+/// `example` GN target in `build/rust/chromium_prelude/example/BUILD.gn` and
+/// `other` which is the `other` GN target in the same file.
 /// ```
 /// chromium::import! {
-///   "//rust/example";
-///   "//rust/example:other";
+///   "//build/rust/chromium_prelude/example";
+///   "//build/rust/chromium_prelude/example:other";
 /// }
 ///
 /// use example::Goat;
 ///
-/// example::foo(Goat::new());
-/// other::foo(Goat::with_age(3));
+/// assert_eq!(example::foo(Goat::new()), 0);
+/// assert_eq!(other::foo(Goat::with_age(3)), 18);
+/// ```
+///
+/// ## Relative paths
+/// GN paths which do not start with `//` are resolved relative to the
+/// directory of the `BUILD.gn` file which defines the current crate. In this
+/// example, the current crate is defined in
+/// `build/rust/chromium_prelude/BUILD.gn`, so the same crates as above are
+/// imported, along with `//build/rust/chromium_prelude/example:util`.
+/// ```
+/// chromium::import! {
+///   "example";
+///   "example:other";
+///   "../chromium_prelude/example:util";
+/// }
+///
+/// assert_eq!(util::strings(), "goat");
 /// ```
 ///
 /// ## Renaming an import
@@ -62,8 +91,8 @@
 /// a different name when imported by using `as`:
 /// ```
 /// chromium::import! {
-///   "//rust/example" as renamed;
-///   "//rust/example:other" as very_renamed;
+///   "//build/rust/chromium_prelude/example" as renamed;
+///   "//build/rust/chromium_prelude/example:other" as very_renamed;
 /// }
 ///
 /// use renamed::Goat;
@@ -72,6 +101,57 @@
 /// very_renamed::foo(Goat::with_age(3));
 /// ```
 ///
+/// ## Grouping imports
+/// Several targets from the same directory can be imported with a single GN
+/// path by listing their names in braces, separated by commas. Each member of
+/// the group may be renamed with `as` and re-exported with `pub` individually.
+/// A `pub` in front of the whole group re-exports every member.
+/// ```
+/// chromium::import! {
+///   "//build/rust/chromium_prelude/example:{example, other as very_renamed, pub util}";
+/// }
+///
+/// example::foo(example::Goat::new());
+/// very_renamed::foo(example::Goat::with_age(3));
+/// ```
+///
+/// ## Toolchain-qualified paths
+/// As in GN, a GN path may end with a toolchain label in parentheses, to import
+/// the target built in that toolchain rather than the current one. This lets a
+/// crate built in one toolchain (such as a host tool built in the
+/// `host_toolchain`) explicitly import a crate from another. The toolchain
+/// applies to every member of a group, and must match the one in `deps`.
+/// This example is not run, since the toolchain depends on the platform.
+/// ```ignore
+/// chromium::import! {
+///   "//build/rust/chromium_prelude/example:other(//build/toolchain/linux:clang_x64)";
+/// }
+/// ```
+///
+/// A target has the same crate name in every toolchain, so the toolchain does
+/// not affect the mangled crate name, and a crate can not import the same
+/// target from two toolchains.
+///
+/// ## Third-party crates
+/// Third-party crates are imported through the `lib` target in their versioned
+/// directory, `//third_party/rust/<crate>/v<epoch>`, and are named after the
+/// crate by default. The path must match the epoch in the `deps` of the GN
+/// target, so the import fails rather than silently using another epoch when
+/// several are present in the build, as in
+/// //build/rust/tests/test_rust_multiple_dep_versions_exe.
+/// ```
+/// chromium::import! {
+///   "//third_party/rust/syn/v2:lib";
+///   "//third_party/rust/quote/v1:lib" as q;
+/// }
+///
+/// let goat: syn::Ident = syn::parse_str("goat").unwrap();
+/// assert_eq!(q::quote!(#goat).to_string(), "goat");
+/// ```
+///
+/// Since the epochs of a crate have the same crate name, only one of them can
+/// be imported by a crate.
+///
 /// ## Re-exporting
 /// When importing and re-exporting a dependency, the usual syntax would be
 /// `pub use my_dependency;`. For first-party crates, this must be done through
@@ -81,15 +161,112 @@
 /// mod module {
 ///
 /// chromium::import! {
-///   pub "//rust/example";
-///   pub "//rust/example:other" as exported_other;
+///   pub "//build/rust/chromium_prelude/example";
+///   pub "//build/rust/chromium_prelude/example:other" as exported_other;
 /// }
 ///
 /// }
 ///
 /// use module::example::Goat;
 ///
-/// module::example::foo(Goat::new())
+/// module::example::foo(Goat::new());
 /// module::exported_other::foo(Goat::with_age(3));
 /// ```
 pub use import_attribute::import;
+
+/// The `chromium::buildflag!()` macro for reading build flags from Rust.
+///
+/// This is the Rust equivalent of the `BUILDFLAG()` macro in C++. It gives the
+/// value of a flag defined by a `buildflag_header` GN target, which must be in
+/// the `deps` of the crate's GN target. See //build/buildflag_header.gni.
+///
+/// The value is a constant expression, so it can be used to initialize a
+/// `const`. Flags set to `true` or `false` are a `bool`, integers are an `i64`
+/// and quoted strings are a `&'static str`. Using a flag name which is not
+/// defined by any `buildflag_header` target in `deps` is a compilation error,
+/// as is using a flag with any other value, such as a C++ expression.
+///
+/// # Example
+/// With this in `rust/example/BUILD.gn`:
+/// ```gn
+/// buildflag_header("example_buildflags") {
+///   header = "example_buildflags.h"
+///   flags = [
+///     "ENABLE_GOATS=$enable_goats",
+///     "GOAT_SERVER_URL=\"https://goats.example.com/\"",
+///   ]
+/// }
+///
+/// rust_static_library("example") {
+///   sources = [ "src/lib.rs" ]
+///   deps = [ ":example_buildflags" ]
+/// }
+/// ```
+/// The flags can be used in `rust/example/src/lib.rs` like so, as they are in
+/// the doctests of the `chromium` crate with the same flags from
+/// `//build/rust/chromium_prelude/example:example_buildflags`:
+/// ```
+/// # fn summon_goats(_url: &str) {}
+/// const GOAT_SERVER_URL: &str = chromium::buildflag!(GOAT_SERVER_URL);
+///
+/// if chromium::buildflag!(ENABLE_GOATS) {
+///     summon_goats(GOAT_SERVER_URL);
+/// }
+/// ```
+pub use import_attribute::buildflag;
+
+/// Returns the GN label of the current crate, such as
+/// `"//rust/example:example"`, as a `&'static str`.
+///
+/// This is useful for logging, metrics names and test fixtures which need to
+/// identify where they come from without hardcoding strings. The target name
+/// is always included in the label. For the unit tests of a crate, this is the
+/// label of the unit test target.
+///
+/// # Example
+/// In the doctests of the `chromium` crate, such as this one:
+/// ```
+/// assert_eq!(
+///     chromium::current_crate_label!(),
+///     "//build/rust/chromium_prelude:chromium_prelude_doctests"
+/// );
+/// ```
+#[macro_export]
+macro_rules! current_crate_label {
+    () => {
+        ::core::env!(
+            "CHROMIUM_GN_LABEL",
+            "current_crate_label!() is only available in first-party GN Rust targets"
+        )
+    };
+}
+
+/// Returns the GN target name of the current crate, such as `"example"` for
+/// `//rust/example:example`, as a `&'static str`.
+///
+/// See `current_crate_label!()` for details.
+#[macro_export]
+macro_rules! current_crate_target_name {
+    () => {
+        ::core::env!(
+            "CHROMIUM_GN_TARGET_NAME",
+            "current_crate_target_name!() is only available in first-party GN Rust targets"
+        )
+    };
+}
+
+/// Returns the mangled crate name of the current crate, such as
+/// `"rust_sexample_cexample"` for `//rust/example:example`, as a
+/// `&'static str`.
+///
+/// This is the name that appears in symbol names and backtraces. See
+/// `current_crate_label!()` for details.
+#[macro_export]
+macro_rules! current_crate_name {
+    () => {
+        ::core::env!(
+            "CHROMIUM_CRATE_NAME",
+            "current_crate_name!() is only available in first-party GN Rust targets"
+        )
+    };
+}
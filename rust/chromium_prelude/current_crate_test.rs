@@ -0,0 +1,19 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+#[test]
+fn test_current_crate() {
+    assert_eq!(
+        chromium::current_crate_label!(),
+        "//build/rust/chromium_prelude:current_crate_unittests"
+    );
+    assert_eq!(chromium::current_crate_target_name!(), "current_crate_unittests");
+    assert_eq!(chromium::current_crate_name!(), "current_crate_unittests");
+}
+
+#[test]
+fn test_current_crate_is_static() {
+    const LABEL: &str = chromium::current_crate_label!();
+    assert!(LABEL.starts_with("//"));
+}
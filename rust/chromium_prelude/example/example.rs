@@ -0,0 +1,28 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+/// A goat, which is passed around by the examples.
+#[derive(Default)]
+pub struct Goat {
+    age: u32,
+}
+
+impl Goat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_age(age: u32) -> Self {
+        Self { age }
+    }
+
+    pub fn age(&self) -> u32 {
+        self.age
+    }
+}
+
+/// Returns the age of the `goat`.
+pub fn foo(goat: Goat) -> u32 {
+    goat.age()
+}
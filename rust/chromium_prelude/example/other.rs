@@ -0,0 +1,12 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+chromium::import! {
+    ":example";
+}
+
+/// Returns the age of the `goat`, in goat years.
+pub fn foo(goat: example::Goat) -> u32 {
+    goat.age() * 6
+}
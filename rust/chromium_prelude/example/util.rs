@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+/// Returns the name of the animal in the examples.
+pub fn strings() -> &'static str {
+    "goat"
+}
@@ -7,10 +7,16 @@ use quote::quote;
 use syn::parse::{Parse, ParseStream};
 use syn::{parse_macro_input, Error, Ident, Lit, Token};
 
+use crate_name_mangling::{escape_non_identifier_chars, split_toolchain};
+
 #[proc_macro]
 pub fn import(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let imports = parse_macro_input!(input as ImportList).imports;
 
+    if let Err(e) = verify_imports_are_deps(&imports) {
+        return e.into_compile_error().into();
+    }
+
     let mut stream = proc_macro2::TokenStream::new();
     for i in imports {
         let public = &i.reexport;
@@ -23,6 +29,109 @@ pub fn import(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     stream.into()
 }
 
+#[proc_macro]
+pub fn buildflag(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let name = parse_macro_input!(input as Ident);
+    match find_buildflag(&name) {
+        Ok(stream) => stream.into(),
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
+/// The environment variable through which GN tells us the path to a file
+/// listing where the `buildflag_header` targets in the crate's deps write their
+/// flags as Rust constants, one path per line. It is generated by
+/// //build/rust/chromium_prelude.gni.
+const BUILDFLAGS_FILE_ENV_VAR: &str = "CHROMIUM_BUILDFLAGS_FILE";
+
+/// Finds the value of the build flag `name` in the Rust constants written by
+/// //build/write_buildflag_header.py, and produces a constant expression for
+/// it. The expression includes the files that were read, so that rustc lists
+/// them in its depfile and the crate is rebuilt when a flag changes.
+fn find_buildflag(name: &Ident) -> syn::Result<proc_macro2::TokenStream> {
+    let Ok(buildflags_file) = std::env::var(BUILDFLAGS_FILE_ENV_VAR) else {
+        return Err(Error::new(
+            name.span(),
+            "buildflag! is only available in first-party GN Rust targets",
+        ));
+    };
+    let read = |path: &str| {
+        std::fs::read_to_string(path)
+            .map_err(|e| Error::new(name.span(), format!("failed to read `{path}`: {e}")))
+    };
+    // `include_bytes!` resolves relative paths from the invoking file, so the
+    // paths, which are relative to the build dir, are made absolute.
+    let cwd = std::env::current_dir()
+        .map_err(|e| Error::new(name.span(), format!("failed to get the build dir: {e}")))?;
+    let mut tracked = Vec::new();
+
+    let paths = read(&buildflags_file)?;
+    tracked.push(cwd.join(&buildflags_file).to_string_lossy().into_owned());
+
+    let name_str = name.to_string();
+    let mut known = Vec::new();
+    for path in paths.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let constants = read(path)?;
+        tracked.push(cwd.join(path).to_string_lossy().into_owned());
+        for line in constants.lines() {
+            // Flags whose value is a C++ expression have no Rust constant.
+            if let Some(flag) = line
+                .strip_prefix("// ")
+                .and_then(|l| l.strip_suffix(" is not representable in Rust."))
+            {
+                if flag == name_str {
+                    return Err(Error::new(
+                        name.span(),
+                        format!(
+                            "build flag `{name_str}` is not representable in Rust, as its value \
+                             is not a bool, integer or simple string literal"
+                        ),
+                    ));
+                }
+                continue;
+            }
+            // Each flag is written as `pub const NAME: TYPE = VALUE;`.
+            let Some((flag, rest)) =
+                line.strip_prefix("pub const ").and_then(|l| l.split_once(": "))
+            else {
+                continue;
+            };
+            if flag != name_str {
+                known.push(flag.to_string());
+                continue;
+            }
+            let Some((ty, value)) = rest.strip_suffix(';').and_then(|r| r.split_once(" = ")) else {
+                return Err(Error::new(name.span(), format!("malformed build flag in `{path}`")));
+            };
+            let parse_error = |e: Error| {
+                Error::new(name.span(), format!("malformed build flag in `{path}`: {e}"))
+            };
+            let ty = match ty {
+                "bool" => quote! { bool },
+                "i64" => quote! { i64 },
+                "&str" => quote! { &'static str },
+                _ => return Err(parse_error(Error::new(name.span(), "unknown type"))),
+            };
+            let value = syn::parse_str::<Lit>(value).map_err(parse_error)?;
+            return Ok(quote! {
+                {
+                    #(const _: &[u8] = include_bytes!(#tracked);)*
+                    const VALUE: #ty = #value;
+                    VALUE
+                }
+            });
+        }
+    }
+
+    let mut message = format!("unknown build flag `{name_str}`");
+    if let Some(closest) = closest_match(&name_str, known.iter().map(String::as_str)) {
+        message.push_str(&format!(", did you mean `{closest}`?"));
+    } else {
+        message.push_str(", is the `buildflag_header` target that defines it in `deps`?");
+    }
+    Err(Error::new(name.span(), message))
+}
+
 struct ImportList {
     imports: Vec<Import>,
 }
@@ -34,25 +143,34 @@ struct Import {
 }
 
 struct GnTarget {
+    /// The absolute GN label, always including the target name, such as
+    /// `//foo/bar:baz`.
+    label: String,
+    /// The absolute label of the toolchain the target is imported from, such
+    /// as `//build/toolchain/linux:clang_x64`. This is `None` only when GN does
+    /// not tell us the current toolchain.
+    toolchain: Option<String>,
+    span: Span,
+    /// The crate name which rustc knows the target by.
     mangled_crate_name: Ident,
+    /// The name the crate is imported as, unless renamed: the GN target name,
+    /// or the crate name of a third-party crate.
     gn_name: Ident,
 }
 
 impl GnTarget {
-    fn parse(s: &str, span: Span) -> Result<GnTarget, String> {
-        if !s.starts_with("//") {
-            return Err(String::from("expected absolute GN path (should start with //)"));
-        }
+    fn parse(s: &str, toolchain: Option<String>, span: Span) -> Result<GnTarget, String> {
+        let absolute;
+        let s = if s.starts_with("//") {
+            s
+        } else {
+            absolute = resolve_relative_label(s, &current_gn_dir()?)?;
+            &absolute
+        };
 
         let mut path: Vec<&str> = s[2..].split('/').collect();
 
         let gn_name = {
-            if path[0..2] == ["third_party", "rust"] {
-                return Err(String::from(
-                    "import! macro should not be used for third_party crates",
-                ));
-            }
-
             let last = path.pop().unwrap();
             let (split_last, gn_name) = match last.split_once(':') {
                 Some((last, name)) => (last, name),
@@ -72,14 +190,320 @@ impl GnTarget {
             }
         }
 
+        if path.starts_with(&["third_party", "rust"]) {
+            return GnTarget::parse_third_party(&path, gn_name, toolchain, span);
+        }
+
         let mangled_crate_name =
             escape_non_identifier_chars(&format!("{}:{gn_name}", path.join("/")))?;
 
+        // The toolchain is not part of the crate name, since a target has the
+        // same crate name in every toolchain. See `mangle_label`.
         Ok(GnTarget {
+            label: format!("//{}:{gn_name}", path.join("/")),
+            toolchain,
+            span,
             mangled_crate_name: Ident::new(&mangled_crate_name, span),
             gn_name: syn::parse_str::<Ident>(gn_name).map_err(|e| format!("{e}"))?,
         })
     }
+
+    /// Parses the path of a third-party crate, which is only supported in the
+    /// versioned form that //build/rust/cargo_crate.gni targets are generated
+    /// in, such as `//third_party/rust/syn/v2:lib`. The crate name is the name
+    /// of the crate's directory, and the epoch is the version directory.
+    ///
+    /// Third-party crates are not mangled, so different epochs of a crate have
+    /// the same crate name, and the import picks the epoch that GN gives to
+    /// rustc, which must be the one in the path.
+    fn parse_third_party(
+        path: &[&str],
+        gn_name: &str,
+        toolchain: Option<String>,
+        span: Span,
+    ) -> Result<GnTarget, String> {
+        let &["third_party", "rust", crate_dir, epoch_dir] = path else {
+            return Err(String::from(
+                "expected a versioned third-party crate path such as \
+                 `//third_party/rust/<crate>/v<epoch>:lib`",
+            ));
+        };
+        let valid_epoch = match epoch_dir.strip_prefix('v') {
+            Some(epoch) => {
+                epoch.starts_with(|c: char| c.is_ascii_digit())
+                    && epoch.chars().all(|c| c.is_ascii_digit() || c == '_')
+            }
+            None => false,
+        };
+        if !valid_epoch {
+            return Err(format!(
+                "expected a version directory such as `v1` or `v0_2` for the epoch of a \
+                 third-party crate, found `{epoch_dir}`"
+            ));
+        }
+        if gn_name != "lib" {
+            return Err(format!(
+                "third-party crates are imported through their `lib` target, found `{gn_name}`"
+            ));
+        }
+
+        let crate_name = syn::parse_str::<Ident>(crate_dir).map_err(|e| format!("{e}"))?;
+        Ok(GnTarget {
+            label: format!("//{}:{gn_name}", path.join("/")),
+            toolchain,
+            span,
+            mangled_crate_name: Ident::new(crate_dir, span),
+            gn_name: crate_name,
+        })
+    }
+}
+
+/// The environment variable through which GN tells us the directory of the
+/// crate being compiled, e.g. `//build/rust/chromium_prelude`. It is set in
+/// //build/rust/rust_target.gni and is used to resolve relative GN paths.
+const GN_DIR_ENV_VAR: &str = "CHROMIUM_GN_DIR";
+
+/// The environment variable through which GN tells us the toolchain the crate
+/// is being compiled in, e.g. `//build/toolchain/linux:clang_x64`. It is set in
+/// //build/rust/rust_target.gni and is the toolchain of imports which do not
+/// name one.
+const GN_TOOLCHAIN_ENV_VAR: &str = "CHROMIUM_GN_TOOLCHAIN";
+
+/// The environment variable through which GN tells us the path to a file
+/// listing the toolchain-qualified GN labels of the crate's dependencies, and of
+/// the crates reachable from them through `public_deps`, one per line. It is
+/// generated by //build/rust/chromium_prelude.gni.
+const GN_DEPS_FILE_ENV_VAR: &str = "CHROMIUM_GN_DEPS_FILE";
+
+/// Returns the GN directory of the crate being compiled, against which relative
+/// GN paths are resolved.
+fn current_gn_dir() -> Result<String, String> {
+    std::env::var(GN_DIR_ENV_VAR).map_err(|_| {
+        format!(
+            "relative GN paths are not supported here since `{GN_DIR_ENV_VAR}` is not set \
+             (use an absolute GN path starting with //)"
+        )
+    })
+}
+
+/// Resolves the `toolchain` of an import (the part in parentheses, as in
+/// `"//foo:bar(//build/toolchain/linux:clang_x64)"`) into an absolute GN label
+/// which always includes the toolchain name.
+///
+/// As in GN, an import without a toolchain is from the current toolchain.
+fn resolve_toolchain(toolchain: Option<&str>) -> Result<Option<String>, String> {
+    let Some(toolchain) = toolchain else {
+        return Ok(std::env::var(GN_TOOLCHAIN_ENV_VAR).ok());
+    };
+    let absolute = match toolchain.strip_prefix("//") {
+        Some(_) => toolchain.to_string(),
+        None => resolve_relative_label(toolchain, &current_gn_dir()?)?,
+    };
+    let label = &absolute[2..];
+    let (dir, name) = match label.split_once(':') {
+        Some((dir, name)) => (dir, name),
+        // As in GN, the name defaults to the last directory name.
+        None => (label, label.rsplit('/').next().unwrap()),
+    };
+    if dir.split('/').any(str::is_empty) || name.is_empty() || name.contains(':') {
+        return Err(format!("invalid toolchain label `{toolchain}`"));
+    }
+    Ok(Some(format!("//{dir}:{name}")))
+}
+
+impl GnTarget {
+    /// The label of the target, qualified with its toolchain when it is known.
+    fn qualified_label(&self) -> String {
+        match &self.toolchain {
+            Some(toolchain) => format!("{}({toolchain})", self.label),
+            None => self.label.clone(),
+        }
+    }
+
+    /// The label of the target, with its toolchain unless that is the current
+    /// toolchain, for use in error messages.
+    fn display_label(&self) -> String {
+        display_label(&self.qualified_label()).to_string()
+    }
+}
+
+/// Strips the current toolchain from a toolchain-qualified `label`, to keep
+/// error messages short in the common case of targets in the same toolchain.
+fn display_label(label: &str) -> &str {
+    match std::env::var(GN_TOOLCHAIN_ENV_VAR) {
+        Ok(toolchain) => label.strip_suffix(&format!("({toolchain})")).unwrap_or(label),
+        Err(_) => label,
+    }
+}
+
+/// Verifies that every imported target is a dependency of the crate being
+/// compiled, when GN provides the list of dependencies.
+///
+/// Without this, rustc reports that it can not find the mangled crate name,
+/// which does not tell the developer which GN label is missing from `deps`.
+fn verify_imports_are_deps(imports: &[Import]) -> syn::Result<()> {
+    let Ok(deps_file) = std::env::var(GN_DEPS_FILE_ENV_VAR) else {
+        return Ok(());
+    };
+    let deps = std::fs::read_to_string(&deps_file).map_err(|e| {
+        Error::new(Span::call_site(), format!("failed to read GN deps from `{deps_file}`: {e}"))
+    })?;
+    let deps: Vec<&str> = deps.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+
+    let mut result: Option<Error> = None;
+    for i in imports {
+        let label = i.target.qualified_label();
+        // The deps are toolchain-qualified. The toolchain of an import is only
+        // unknown when GN does not tell us the current one, then any will do.
+        let found = match &i.target.toolchain {
+            Some(_) => deps.contains(&label.as_str()),
+            None => deps.iter().any(|dep| split_toolchain(dep).is_ok_and(|(d, _)| d == label)),
+        };
+        if found {
+            continue;
+        }
+        let mut message =
+            format!("`{}` is not in the GN deps of this crate", i.target.display_label());
+        if let Some(closest) = closest_match(&label, deps.iter().copied()) {
+            message.push_str(&format!(", did you mean `{}`?", display_label(closest)));
+        } else {
+            message.push_str(", add it to `deps` in the BUILD.gn file");
+        }
+        let error = Error::new(i.target.span, message);
+        match &mut result {
+            Some(result) => result.combine(error),
+            None => result = Some(error),
+        }
+    }
+    result.map_or(Ok(()), Err)
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be what was
+/// meant. As in rustc's suggestions, that is within a third of its length.
+fn closest_match<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = name.chars().count().max(3) / 3;
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Computes the Levenshtein distance between `a` and `b`, which is used to
+/// suggest the closest GN label or build flag.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Resolves a relative GN `label` (such as `:name` or `../dir:name`) against
+/// `current_dir`, producing an absolute label (such as `//dir:name`).
+///
+/// As in GN, `.` and `..` path components are supported, and when no target
+/// name is given it is left off so that it defaults to the last directory name.
+fn resolve_relative_label(label: &str, current_dir: &str) -> Result<String, String> {
+    let Some(current_dir) = current_dir.strip_prefix("//") else {
+        return Err(format!("invalid GN directory `{current_dir}` for the current crate"));
+    };
+
+    let (dir, name) = match label.split_once(':') {
+        Some((dir, name)) => (dir, Some(name)),
+        None => (label, None),
+    };
+
+    let mut components: Vec<&str> = current_dir.split('/').filter(|c| !c.is_empty()).collect();
+    if !dir.is_empty() {
+        for c in dir.split('/') {
+            match c {
+                "." => {}
+                ".." => {
+                    if components.pop().is_none() {
+                        return Err(String::from("relative GN path goes above the source root"));
+                    }
+                }
+                "" => return Err(String::from("unexpected empty GN path component")),
+                c => components.push(c),
+            }
+        }
+    }
+    if components.is_empty() {
+        return Err(String::from("relative GN path resolves to the source root"));
+    }
+
+    let dir = components.join("/");
+    Ok(match name {
+        Some(name) => format!("//{dir}:{name}"),
+        None => format!("//{dir}"),
+    })
+}
+
+/// A single member of a brace-grouped import, such as `pub strings as s` in
+/// `"//base/rust:{json, pub strings as s}"`.
+struct GroupMember<'a> {
+    name: &'a str,
+    alias: Option<&'a str>,
+    reexport: bool,
+}
+
+/// Splits a brace-grouped GN path such as `"//base/rust:{json, strings as s}"`
+/// into its directory (`//base/rust`) and members.
+///
+/// Returns `Ok(None)` if `label` is not brace-grouped.
+fn split_group(label: &str) -> Result<Option<(&str, Vec<GroupMember<'_>>)>, String> {
+    if !label.contains('{') {
+        return Ok(None);
+    }
+    let Some((dir, group)) = label.split_once(":{") else {
+        return Err(String::from("expected `{` to directly follow the `:` of a GN path"));
+    };
+    let Some(group) = group.strip_suffix('}') else {
+        return Err(String::from("expected `}` at the end of a brace-grouped GN path"));
+    };
+
+    let mut entries: Vec<&str> = group.split(',').map(str::trim).collect();
+    // Allow a trailing comma, as in Rust `use` groups.
+    if entries.len() > 1 && entries.last() == Some(&"") {
+        entries.pop();
+    }
+
+    let mut members = Vec::new();
+    for entry in entries {
+        let words: Vec<&str> = entry.split_whitespace().collect();
+        let (reexport, words) = match words.split_first() {
+            Some((&"pub", rest)) => (true, rest),
+            _ => (false, &words[..]),
+        };
+        let (name, alias) = match words {
+            [name] => (*name, None),
+            [name, "as", alias] => (*name, Some(*alias)),
+            [] if reexport => {
+                return Err(String::from("expected a name after `pub` in brace group"))
+            }
+            [] => return Err(String::from("unexpected empty entry in brace group")),
+            _ => {
+                return Err(format!(
+                    "expected `name`, `name as alias` or `pub name` in brace group, found \
+                     `{entry}`"
+                ));
+            }
+        };
+        if name.contains([':', '/', '{', '}']) {
+            return Err(format!("expected a GN target name in brace group, found `{name}`"));
+        }
+        members.push(GroupMember { name, alias, reexport });
+    }
+
+    Ok(Some((dir, members)))
 }
 
 impl Parse for ImportList {
@@ -91,19 +515,11 @@ impl Parse for ImportList {
 
             let str_span = input.span();
 
-            let target = match Lit::parse(input) {
+            let label = match Lit::parse(input) {
                 Err(_) => {
                     return Err(Error::new(str_span, "expected a GN path as a string literal"));
                 }
-                Ok(Lit::Str(label)) => match GnTarget::parse(&label.value(), str_span) {
-                    Ok(target) => target,
-                    Err(e) => {
-                        return Err(Error::new(
-                            str_span,
-                            format!("invalid GN path {}: {}", quote::quote! {#label}, e),
-                        ));
-                    }
-                },
+                Ok(Lit::Str(label)) => label,
                 Ok(lit) => {
                     return Err(Error::new(
                         str_span,
@@ -114,59 +530,84 @@ impl Parse for ImportList {
                     ));
                 }
             };
+            let invalid_path = |e: String| {
+                Error::new(str_span, format!("invalid GN path {}: {}", quote! {#label}, e))
+            };
+
             let alias = match <Token![as]>::parse(input) {
                 Ok(_) => Some(Ident::parse(input)?),
                 Err(_) => None,
             };
             <syn::Token![;]>::parse(input)?;
 
-            imports.push(Import { target, alias, reexport });
-        }
+            // A toolchain applies to every member of a brace group, as in
+            // `"//foo:{bar, baz}(//build/toolchain/linux:clang_x64)"`.
+            let label_value = label.value();
+            let (path, toolchain) = split_toolchain(&label_value).map_err(invalid_path)?;
+            let toolchain = resolve_toolchain(toolchain).map_err(invalid_path)?;
 
-        Ok(Self { imports })
-    }
-}
+            match split_group(path).map_err(invalid_path)? {
+                None => {
+                    let target =
+                        GnTarget::parse(path, toolchain, str_span).map_err(invalid_path)?;
+                    imports.push(Import { target, alias, reexport });
+                }
+                Some((dir, members)) => {
+                    if let Some(alias) = alias {
+                        return Err(Error::new(
+                            alias.span(),
+                            "a brace-grouped import can not be renamed as a whole, rename each \
+                             member inside the braces instead",
+                        ));
+                    }
+                    for member in members {
+                        let target = GnTarget::parse(
+                            &format!("{dir}:{}", member.name),
+                            toolchain.clone(),
+                            str_span,
+                        )
+                        .map_err(invalid_path)?;
+                        let alias = match member.alias {
+                            Some(alias) => Some(
+                                syn::parse_str::<Ident>(alias)
+                                    .map_err(|e| invalid_path(format!("{e}")))?,
+                            ),
+                            None => None,
+                        };
+                        let reexport = match member.reexport {
+                            true => Some(Token![pub](str_span)),
+                            false => reexport,
+                        };
+                        imports.push(Import { target, alias, reexport });
+                    }
+                }
+            }
+        }
 
-/// Escapes non-identifier characters in `symbol`.
-///
-/// Importantly, this is
-/// [an injective function](https://en.wikipedia.org/wiki/Injective_function)
-/// which means that different inputs are never mapped to the same output.
-///
-/// This is based on a similar function in
-/// https://github.com/google/crubit/blob/22ab04aef9f7cc56d8600c310c7fe20999ffc41b/common/code_gen_utils.rs#L59-L71
-/// The main differences are:
-///
-/// * Only a limited set of special characters is supported, because this makes
-///   it easier to replicate the escaping algorithm in `.gni` files, using just
-///   `string_replace` calls.
-/// * No dependency on `unicode_ident` crate means that instead of
-///   `is_xid_continue` a more restricted call to `char::is_ascii_alphanumeric`
-///   is used.
-/// * No support for escaping leading digits.
-/// * The escapes are slightly different (e.g. `/` frequently appears in GN
-///   paths and therefore here we map it to a nice `_s` rather than to `_x002f`)
-fn escape_non_identifier_chars(symbol: &str) -> Result<String, String> {
-    assert!(!symbol.is_empty()); // Caller is expected to verify.
-    if symbol.chars().next().unwrap().is_ascii_digit() {
-        return Err("Leading digits are not supported".to_string());
-    }
-
-    // Escaping every character can at most double the size of the string.
-    let mut result = String::with_capacity(symbol.len() * 2);
-    for c in symbol.chars() {
-        // NOTE: TargetName=>CrateName mangling algorithm should be updated
-        // simultaneously in 3 places: here, //build/rust/rust_target.gni,
-        // //build/rust/rust_static_library.gni.
-        match c {
-            '_' => result.push_str("_u"),
-            '/' => result.push_str("_s"),
-            ':' => result.push_str("_c"),
-            '-' => result.push_str("_d"),
-            c if c.is_ascii_alphanumeric() => result.push(c),
-            _ => return Err(format!("Unsupported character in GN path component: `{c}`")),
+        // rustc can only be given one crate for each crate name.
+        for (n, i) in imports.iter().enumerate() {
+            let Some(other) = imports[..n].iter().find(|other| {
+                other.target.mangled_crate_name == i.target.mangled_crate_name
+                    && other.target.qualified_label() != i.target.qualified_label()
+            }) else {
+                continue;
+            };
+            let message = if other.target.label == i.target.label {
+                format!(
+                    "`{}` can not be imported from more than one toolchain, since its crate \
+                     has the same name in each",
+                    i.target.label
+                )
+            } else {
+                format!(
+                    "`{}` can not be imported along with `{}`, since they are epochs of the \
+                     same crate",
+                    i.target.label, other.target.label
+                )
+            };
+            return Err(Error::new(i.target.span, message));
         }
-    }
 
-    Ok(result)
+        Ok(Self { imports })
+    }
 }
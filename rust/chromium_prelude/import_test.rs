@@ -22,6 +22,34 @@ mod test_as {
     }
 }
 
+mod test_relative {
+    chromium::import! {
+        ":import_test_lib";
+        "../chromium_prelude:import_test_lib" as parent_relative;
+        ".:import_test_lib" as dot_relative;
+    }
+
+    pub fn import_test() {
+        import_test_lib::import_test_lib();
+        parent_relative::import_test_lib();
+        dot_relative::import_test_lib();
+    }
+}
+
+mod test_group {
+    chromium::import! {
+        "//build/rust/chromium_prelude:{import_test_lib, import_test_other_lib as other}";
+        ":{import_test_lib as relative, pub import_test_other_lib,}";
+    }
+
+    pub fn import_test() {
+        import_test_lib::import_test_lib();
+        other::import_test_other_lib();
+        relative::import_test_lib();
+        import_test_other_lib::import_test_other_lib();
+    }
+}
+
 mod test_pub {
     chromium::import! {
         pub "//build/rust/chromium_prelude:import_test_lib" as library;
@@ -29,7 +57,14 @@ mod test_pub {
 }
 
 fn main() {
+    assert_eq!(chromium::current_crate_label!(), "//build/rust/chromium_prelude:import_test");
+    assert_eq!(chromium::current_crate_target_name!(), "import_test");
+    assert_eq!(chromium::current_crate_name!(), "build_srust_schromium_uprelude_cimport_utest");
+
     test_direct::import_test();
     test_as::import_test();
+    test_relative::import_test();
+    test_group::import_test();
+    test_group::import_test_other_lib::import_test_other_lib();
     test_pub::library::import_test_lib();
 }
@@ -0,0 +1,6 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The build flag is named by an identifier, not a string.
+const _: bool = chromium::buildflag!("IS_ENABLED"); //~ ERROR expected identifier
@@ -0,0 +1,6 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// Build flags must be defined by a `buildflag_header` target in the `deps`.
+const _: bool = chromium::buildflag!(IS_ENABLD); //~ ERROR unknown build flag `IS_ENABLD`, did you mean `IS_ENABLED`?
@@ -0,0 +1,6 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// C++ expressions, unlike literals, have no Rust equivalent.
+const _: i64 = chromium::buildflag!(SOME_EXPRESSION); //~ ERROR build flag `SOME_EXPRESSION` is not representable in Rust
@@ -0,0 +1,7 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+chromium::import! {
+    "//build/rust//chromium_prelude:import_test_lib"; //~ ERROR unexpected empty GN path component
+}
@@ -0,0 +1,7 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+chromium::import! {
+    ":{import_test_lib, {import_test_other_lib}}"; //~ ERROR expected a GN target name in brace group, found `{import_test_other_lib}`
+}
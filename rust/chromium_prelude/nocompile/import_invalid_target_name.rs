@@ -0,0 +1,9 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// GN target names may contain `-`, but the target name must be a Rust
+// identifier to be used as the name of the imported crate.
+chromium::import! {
+    "//build/rust/chromium_prelude:import-test-lib"; //~ ERROR invalid GN path "//build/rust/chromium_prelude:import-test-lib"
+}
@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// Crate names can not start with a digit, so neither can GN paths.
+chromium::import! {
+    "//1build/rust/chromium_prelude:import_test_lib"; //~ ERROR Leading digits are not supported
+}
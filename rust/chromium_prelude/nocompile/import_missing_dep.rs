@@ -0,0 +1,9 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// Every imported target must be listed in the `deps` of the crate.
+chromium::import! {
+    ":import_test_lib";
+    ":import_test_other_lib"; //~ ERROR `//build/rust/chromium_prelude:import_test_other_lib` is not in the GN deps of this crate
+}
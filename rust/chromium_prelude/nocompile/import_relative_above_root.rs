@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The crate is in //build/rust/chromium_prelude, three levels deep.
+chromium::import! {
+    "../../../../build:import_test_lib"; //~ ERROR relative GN path goes above the source root
+}
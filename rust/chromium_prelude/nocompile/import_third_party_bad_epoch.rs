@@ -0,0 +1,7 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+chromium::import! {
+    "//third_party/rust/syn/2:lib"; //~ ERROR expected a version directory such as `v1` or `v0_2`
+}
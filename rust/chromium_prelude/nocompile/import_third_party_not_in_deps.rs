@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The epoch must be the one in `deps`, rather than any epoch of the crate.
+chromium::import! {
+    "//third_party/rust/syn/v1:lib"; //~ ERROR `//third_party/rust/syn/v1:lib` is not in the GN deps of this crate, did you mean `//third_party/rust/syn/v2:lib`?
+}
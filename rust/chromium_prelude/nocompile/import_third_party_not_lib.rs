@@ -0,0 +1,7 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+chromium::import! {
+    "//third_party/rust/syn/v2:syn"; //~ ERROR third-party crates are imported through their `lib` target
+}
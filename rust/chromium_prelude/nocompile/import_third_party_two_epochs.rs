@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+chromium::import! {
+    "//third_party/rust/syn/v1:lib";
+    "//third_party/rust/syn/v2:lib"; //~ ERROR `//third_party/rust/syn/v2:lib` can not be imported along with `//third_party/rust/syn/v1:lib`
+}
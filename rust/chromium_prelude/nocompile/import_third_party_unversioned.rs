@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// Third-party crates are only importable through their versioned directory.
+chromium::import! {
+    "//third_party/rust/syn:lib"; //~ ERROR expected a versioned third-party crate path
+}
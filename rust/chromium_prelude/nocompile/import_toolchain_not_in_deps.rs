@@ -0,0 +1,8 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The target is only in `deps` in the current toolchain.
+chromium::import! {
+    ":import_test_lib(//build/toolchain/does_not:exist)"; //~ ERROR `//build/rust/chromium_prelude:import_test_lib(//build/toolchain/does_not:exist)` is not in the GN deps of this crate, did you mean `//build/rust/chromium_prelude:import_test_lib`?
+}
@@ -0,0 +1,9 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The crate has the same name in both toolchains.
+chromium::import! {
+    ":import_test_lib";
+    ":import_test_lib(//build/toolchain/does_not:exist)"; //~ ERROR can not be imported from more than one toolchain
+}
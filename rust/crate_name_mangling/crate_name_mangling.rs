@@ -0,0 +1,280 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Conversions between the GN label of a first-party Rust target and its
+//! mangled crate name, e.g. `//build/rust/tests:foo` and
+//! `build_srust_stests_cfoo`.
+//!
+//! This is used by the `chromium::import!` macro to find the crate for a GN
+//! label, and by the `crate_name_mangling_tool` binary to find the GN label
+//! for a crate name seen in backtraces, symbol names or build logs.
+
+/// Mangles an absolute GN `label` (such as `//foo/bar:baz`) into the crate
+/// name that //build/rust/rust_target.gni gives to the target.
+///
+/// The target name must be given explicitly, as it is when `label` comes from
+/// `get_label_info(..., "label_no_toolchain")` in GN.
+///
+/// A toolchain-qualified label (such as
+/// `//foo/bar:baz(//build/toolchain/linux:clang_x64)`) mangles to the same
+/// crate name as the label without its toolchain: a target has the same crate
+/// name in every toolchain it is built in.
+pub fn mangle_label(label: &str) -> Result<String, String> {
+    let (label, _toolchain) = split_toolchain(label)?;
+    let Some(label) = label.strip_prefix("//") else {
+        return Err(format!("expected absolute GN label (should start with //): `{label}`"));
+    };
+    match label.split_once(':') {
+        Some((dir, name)) if !dir.is_empty() && !name.is_empty() => {
+            if !is_label_dir(dir) {
+                return Err(format!("unexpected empty GN path component in `//{label}`"));
+            }
+        }
+        _ => return Err(format!("expected GN label of the form `//dir:name`: `//{label}`")),
+    }
+    escape_non_identifier_chars(label)
+}
+
+/// Splits a toolchain-qualified GN `label` (such as
+/// `//foo/bar:baz(//build/toolchain/linux:clang_x64)`) into the label without
+/// its toolchain (`//foo/bar:baz`) and the toolchain label, if there is one.
+pub fn split_toolchain(label: &str) -> Result<(&str, Option<&str>), String> {
+    let Some((label_no_toolchain, toolchain)) = label.split_once('(') else {
+        if label.contains(')') {
+            return Err(format!("unexpected `)` in GN label: `{label}`"));
+        }
+        return Ok((label, None));
+    };
+    match toolchain.strip_suffix(')') {
+        Some(toolchain) if !toolchain.is_empty() && !toolchain.contains(['(', ')']) => {
+            Ok((label_no_toolchain, Some(toolchain)))
+        }
+        _ => Err(format!("expected a toolchain label in parentheses at the end of `{label}`")),
+    }
+}
+
+/// Recovers the absolute GN label (such as `//foo/bar:baz`) of a target from
+/// its mangled crate name. This is the inverse of `mangle_label`.
+///
+/// Crate names which were not produced by mangling a GN label, such as those
+/// of third-party crates or targets with an explicit `crate_name`, are
+/// rejected.
+pub fn demangle_crate_name(crate_name: &str) -> Result<String, String> {
+    let label = unescape_non_identifier_chars(crate_name)?;
+    match label.split_once(':') {
+        Some((dir, name)) if is_label_dir(dir) && !name.is_empty() && !name.contains(':') => {
+            Ok(format!("//{label}"))
+        }
+        _ => Err(format!("`{crate_name}` is not a crate name mangled from a GN label")),
+    }
+}
+
+/// Whether `dir` is the directory of an absolute GN label without its leading
+/// `//`, such as `foo/bar`: it has no empty components, so it can not start or
+/// end with a `/`, nor contain `//`.
+fn is_label_dir(dir: &str) -> bool {
+    dir.split('/').all(|component| !component.is_empty())
+}
+
+/// Escapes non-identifier characters in `symbol`.
+///
+/// Importantly, this is
+/// [an injective function](https://en.wikipedia.org/wiki/Injective_function)
+/// which means that different inputs are never mapped to the same output.
+///
+/// This is based on a similar function in
+/// https://github.com/google/crubit/blob/22ab04aef9f7cc56d8600c310c7fe20999ffc41b/common/code_gen_utils.rs#L59-L71
+/// The main differences are:
+///
+/// * Only a limited set of special characters is supported, because this makes
+///   it easier to replicate the escaping algorithm in `.gni` files, using just
+///   `string_replace` calls.
+/// * No dependency on `unicode_ident` crate means that instead of
+///   `is_xid_continue` a more restricted call to `char::is_ascii_alphanumeric`
+///   is used.
+/// * No support for escaping leading digits.
+/// * The escapes are slightly different (e.g. `/` frequently appears in GN
+///   paths and therefore here we map it to a nice `_s` rather than to `_x002f`)
+pub fn escape_non_identifier_chars(symbol: &str) -> Result<String, String> {
+    assert!(!symbol.is_empty()); // Caller is expected to verify.
+    if symbol.chars().next().unwrap().is_ascii_digit() {
+        return Err("Leading digits are not supported".to_string());
+    }
+
+    // Escaping every character can at most double the size of the string.
+    let mut result = String::with_capacity(symbol.len() * 2);
+    for c in symbol.chars() {
+        // NOTE: TargetName=>CrateName mangling algorithm should be updated
+        // simultaneously in 3 places: here, //build/rust/rust_target.gni,
+        // //build/rust/rust_static_library.gni. The reverse mapping in
+        // `unescape_non_identifier_chars` must be kept in sync too.
+        match c {
+            '_' => result.push_str("_u"),
+            '/' => result.push_str("_s"),
+            ':' => result.push_str("_c"),
+            '-' => result.push_str("_d"),
+            c if c.is_ascii_alphanumeric() => result.push(c),
+            _ => return Err(format!("Unsupported character in GN path component: `{c}`")),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Reverses `escape_non_identifier_chars`.
+///
+/// Since every escape sequence is an `_` followed by a single character that
+/// identifies the escaped character, the mangled string can be decoded from
+/// left to right without any ambiguity.
+pub fn unescape_non_identifier_chars(mangled: &str) -> Result<String, String> {
+    if mangled.is_empty() {
+        return Err("Empty crate name".to_string());
+    }
+    if mangled.chars().next().unwrap().is_ascii_digit() {
+        return Err("Leading digits are not supported".to_string());
+    }
+
+    let mut result = String::with_capacity(mangled.len());
+    let mut chars = mangled.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '_' => match chars.next() {
+                Some('u') => result.push('_'),
+                Some('s') => result.push('/'),
+                Some('c') => result.push(':'),
+                Some('d') => result.push('-'),
+                Some(e) => return Err(format!("Unknown escape sequence in crate name: `_{e}`")),
+                None => return Err("Unterminated escape sequence in crate name".to_string()),
+            },
+            c if c.is_ascii_alphanumeric() => result.push(c),
+            _ => return Err(format!("Unsupported character in crate name: `{c}`")),
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_mangle_label() {
+        assert_eq!(
+            mangle_label("//build/rust/tests/test_rust_metadata:lib").unwrap(),
+            "build_srust_stests_stest_urust_umetadata_clib"
+        );
+        assert_eq!(mangle_label("//foo-bar:baz").unwrap(), "foo_dbar_cbaz");
+        assert!(mangle_label("foo:bar").is_err());
+        assert!(mangle_label("//foo").is_err());
+        assert!(mangle_label("//foo:").is_err());
+        assert!(mangle_label("//1foo:bar").is_err());
+        assert!(mangle_label("//foo:bar.baz").is_err());
+        // Empty path components.
+        assert!(mangle_label("///foo:bar").is_err());
+        assert!(mangle_label("//foo//x:bar").is_err());
+        assert!(mangle_label("//foo/:bar").is_err());
+    }
+
+    #[test]
+    fn test_mangle_toolchain_qualified_label() {
+        assert_eq!(
+            mangle_label("//foo-bar:baz(//build/toolchain/linux:clang_x64)").unwrap(),
+            "foo_dbar_cbaz"
+        );
+        assert!(mangle_label("//foo:bar()").is_err());
+        assert!(mangle_label("//foo:bar(//build/toolchain/linux:clang_x64").is_err());
+    }
+
+    #[test]
+    fn test_split_toolchain() {
+        assert_eq!(split_toolchain("//foo:bar").unwrap(), ("//foo:bar", None));
+        assert_eq!(
+            split_toolchain("//foo:bar(//build/toolchain/linux:clang_x64)").unwrap(),
+            ("//foo:bar", Some("//build/toolchain/linux:clang_x64"))
+        );
+        assert_eq!(split_toolchain(":bar(:host)").unwrap(), (":bar", Some(":host")));
+        assert!(split_toolchain("//foo:bar()").is_err());
+        assert!(split_toolchain("//foo:bar(//tc").is_err());
+        assert!(split_toolchain("//foo:bar(//tc)x").is_err());
+        assert!(split_toolchain("//foo:bar((//tc))").is_err());
+        assert!(split_toolchain("//foo:bar)").is_err());
+    }
+
+    #[test]
+    fn test_demangle_crate_name() {
+        assert_eq!(
+            demangle_crate_name("build_srust_stests_stest_urust_umetadata_clib").unwrap(),
+            "//build/rust/tests/test_rust_metadata:lib"
+        );
+        assert_eq!(demangle_crate_name("foo_dbar_cbaz").unwrap(), "//foo-bar:baz");
+        // Third-party and explicitly named crates.
+        assert!(demangle_crate_name("serde").is_err());
+        assert!(demangle_crate_name("chromium").is_err());
+        // Not the output of the mangling.
+        assert!(demangle_crate_name("foo_cbar_cbaz").is_err());
+        assert!(demangle_crate_name("foo_x_cbar").is_err());
+        assert!(demangle_crate_name("foo_cbar_").is_err());
+        assert!(demangle_crate_name("_cfoo").is_err());
+        // Empty path components.
+        assert!(demangle_crate_name("_sfoo_cbar").is_err());
+        assert!(demangle_crate_name("foo_s_sx_cbar").is_err());
+        assert!(demangle_crate_name("foo_s_cbar").is_err());
+        assert!(demangle_crate_name("").is_err());
+    }
+
+    /// Enumerates every string up to `max_len` characters long made from
+    /// `alphabet`.
+    fn all_strings(alphabet: &[char], max_len: usize) -> Vec<String> {
+        let mut result = vec![String::new()];
+        let mut last = vec![String::new()];
+        for _ in 0..max_len {
+            last =
+                last.iter().flat_map(|s| alphabet.iter().map(move |c| format!("{s}{c}"))).collect();
+            result.extend(last.iter().cloned());
+        }
+        result
+    }
+
+    #[test]
+    fn test_escape_is_injective() {
+        // Every supported escaped character, along with the characters used in
+        // escape sequences, so that any collision would be found.
+        let alphabet = ['a', 'c', 's', 'u', 'd', '_', '/', ':', '-'];
+        let mut seen = HashMap::new();
+        for symbol in all_strings(&alphabet, 5).into_iter().filter(|s| !s.is_empty()) {
+            let escaped = escape_non_identifier_chars(&symbol).unwrap();
+            assert_eq!(unescape_non_identifier_chars(&escaped).unwrap(), symbol);
+            if let Some(other) = seen.insert(escaped.clone(), symbol.clone()) {
+                panic!("`{symbol}` and `{other}` both escape to `{escaped}`");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unescape_round_trip() {
+        // Every crate name that unescapes successfully must escape back to the
+        // same name, so no two crate names demangle to the same GN label.
+        let alphabet = ['a', 'c', 's', 'u', 'd', 'x', '_'];
+        for name in all_strings(&alphabet, 6).into_iter().filter(|s| !s.is_empty()) {
+            if let Ok(symbol) = unescape_non_identifier_chars(&name) {
+                assert_eq!(escape_non_identifier_chars(&symbol).unwrap(), name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_label_round_trip() {
+        for label in [
+            "//build/rust/chromium_prelude:import_test_lib",
+            "//a:b",
+            "//a/_/-:_",
+            "//third-party_ish/x1:y_2",
+        ] {
+            let crate_name = mangle_label(label).unwrap();
+            assert_eq!(demangle_crate_name(&crate_name).unwrap(), label);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Converts between GN labels and the mangled crate names of first-party Rust
+//! targets.
+//!
+//! Usage:
+//!   crate_name_mangling_tool demangle <crate name>...
+//!   crate_name_mangling_tool mangle <GN label>...
+//!
+//! When no names are given on the command line, they are read from stdin, one
+//! per line, which is convenient for crash triage and build log scripts. Each
+//! result is printed on its own line. Names that can not be converted are
+//! reported on stderr, and cause a non-zero exit code.
+
+use std::io::BufRead;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let convert: fn(&str) -> Result<String, String> = match args.next().as_deref() {
+        Some("demangle") => crate_name_mangling::demangle_crate_name,
+        Some("mangle") => crate_name_mangling::mangle_label,
+        _ => {
+            eprintln!("usage: crate_name_mangling_tool (demangle|mangle) [NAME]...");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut inputs: Vec<String> = args.collect();
+    if inputs.is_empty() {
+        inputs = std::io::stdin().lock().lines().map_while(Result::ok).collect();
+    }
+
+    let mut status = ExitCode::SUCCESS;
+    for input in inputs.iter().map(|i| i.trim()).filter(|i| !i.is_empty()) {
+        match convert(input) {
+            Ok(output) => println!("{output}"),
+            Err(e) => {
+                eprintln!("{input}: {e}");
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    status
+}
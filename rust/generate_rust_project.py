@@ -0,0 +1,224 @@
+#!/usr/bin/env python3
+
+# Copyright 2023 The Chromium Authors
+# Use of this source code is governed by a BSD-style license that can be
+# found in the LICENSE file.
+
+# Generates a rust-project.json for rust-analyzer from the crates collected by
+# //build/rust/rust_project.gni. See there for details, and
+# https://rust-analyzer.github.io/manual.html#non-cargo-based-projects for the
+# format.
+
+import argparse
+import json
+import os
+import posixpath
+import re
+import sys
+
+# Set up path to be able to import action_helpers.
+sys.path.append(
+    os.path.join(os.path.dirname(os.path.abspath(__file__)), os.pardir,
+                 os.pardir, 'build'))
+import action_helpers
+from rustc_wrapper import expand_rustenv_files
+
+# A GN label, with an optional toolchain in parentheses.
+GN_LABEL = re.compile(r"([^()]*)(?:\((.*)\))?")
+
+# Environment variables holding paths relative to the build dir, which are
+# made absolute since rust-analyzer does not run from the build dir. OUT_DIR
+# is instead relative to the directory of the crate root.
+BUILD_DIR_RELATIVE_ENV_VARS = {
+    "CARGO_MANIFEST_DIR",
+    "CARGO_MANIFEST_PATH",
+    "CHROMIUM_BUILDFLAGS_FILE",
+    "CHROMIUM_GN_DEPS_FILE",
+}
+
+
+def resolve_label(label, current_dir, current_toolchain):
+  """Returns the full form of the GN `label`, such as
+  `//foo/bar:baz(//build/toolchain/linux:clang_x64)`, resolving relative
+  labels against `current_dir` and `current_toolchain`."""
+  path, toolchain = GN_LABEL.fullmatch(label).groups()
+  if toolchain:
+    toolchain = resolve_label(toolchain, current_dir, None)
+  else:
+    toolchain = current_toolchain
+  path, _, name = path.partition(":")
+  if not path:
+    path = current_dir
+  elif not path.startswith("//"):
+    path = "/" + posixpath.normpath(current_dir[1:] + "/" + path)
+  path = path.rstrip("/")
+  if not name:
+    name = posixpath.basename(path)
+  if toolchain:
+    return f"{path}:{name}({toolchain})"
+  return f"{path}:{name}"
+
+
+def split_label(label):
+  """Returns the directory, target name and toolchain of a full GN label."""
+  path, toolchain = GN_LABEL.fullmatch(label).groups()
+  directory, _, name = path.partition(":")
+  return directory, name, toolchain
+
+
+def is_in_dir(path, directory):
+  return path == directory or path.startswith(directory + "/")
+
+
+def expand_rustflags_files(rustflags):
+  """Replaces each `@<path>` entry in `rustflags` with the flags listed in that
+  file, one per line, as rustc does."""
+  expanded = []
+  for flag in rustflags:
+    if flag.startswith("@"):
+      with open(flag[1:], encoding="utf-8") as f:
+        expanded.extend(f.read().splitlines())
+    else:
+      expanded.append(flag)
+  return expanded
+
+
+def parse_cfgs(rustflags):
+  """Returns the `--cfg` values in `rustflags`, including the cfgs set by build
+  scripts."""
+  cfgs = []
+  flags = iter(expand_rustflags_files(rustflags))
+  for flag in flags:
+    if flag == "--cfg":
+      cfgs.append(next(flags, ""))
+    elif flag.startswith("--cfg="):
+      cfgs.append(flag[len("--cfg="):])
+    elif flag == "--test":
+      cfgs.append("test")
+  return cfgs
+
+
+def crate_env(crate, build_dir):
+  """Returns the environment variables for compiling `crate`, with paths made
+  absolute."""
+  root_dir = os.path.dirname(os.path.join(build_dir, crate["root_module"]))
+  env = {}
+  # The `@file` entries are written by build scripts, which have run since
+  # they are in the deps of the rust_project() target.
+  for item in expand_rustenv_files(crate["env"]):
+    key, _, value = item.partition("=")
+    if key == "OUT_DIR":
+      value = os.path.normpath(os.path.join(root_dir, value))
+    elif key in BUILD_DIR_RELATIVE_ENV_VARS:
+      value = os.path.normpath(os.path.join(build_dir, value))
+    env[key] = value
+  return env
+
+
+def generate_rust_project(crates, aliases, build_dir, sysroot, exclude_dirs):
+  """Returns the rust-project.json contents for the `crates` and `aliases`
+  collected from the GN metadata."""
+  crates = [
+      crate for crate in crates if not any(
+          is_in_dir(split_label(crate["label"])[0], d) for d in exclude_dirs)
+  ]
+  crate_index = {crate["label"]: i for i, crate in enumerate(crates)}
+
+  def find_crate(label):
+    label = aliases.get(label, label)
+    return crate_index.get(label)
+
+  project_crates = []
+  for crate in crates:
+    current_dir, target_name, current_toolchain = split_label(crate["label"])
+    alias_names = {
+        resolve_label(label, current_dir, current_toolchain): name
+        for name, label in crate["aliased_deps"].items()
+    }
+    deps = []
+    for dep in crate["crate_deps"]:
+      # Deps which are not Rust crates, such as C++ targets, are skipped.
+      index = find_crate(dep)
+      if index is None:
+        continue
+      name = alias_names.get(dep, crates[index]["crate_name"])
+      if {"crate": index, "name": name} not in deps:
+        deps.append({"crate": index, "name": name})
+
+    root_module = os.path.normpath(
+        os.path.join(build_dir, crate["root_module"]))
+    env = crate_env(crate, build_dir)
+    # Generated files which are `include!`ed from OUT_DIR must be within the
+    # crate's sources for rust-analyzer to load them.
+    include_dirs = [os.path.dirname(root_module)]
+    if "OUT_DIR" in env:
+      include_dirs.append(env["OUT_DIR"])
+    project_crate = {
+        # The GN label, without the toolchain.
+        "display_name": f"{current_dir}:{target_name}",
+        "root_module": root_module,
+        "edition": crate["edition"],
+        "deps": deps,
+        "cfg": parse_cfgs(crate["rustflags"]),
+        "env": env,
+        "is_workspace_member": crate["is_workspace_member"],
+        "is_proc_macro": crate["is_proc_macro"],
+        "source": {
+            "include_dirs": include_dirs,
+            "exclude_dirs": [],
+        },
+    }
+    # The target is unknown for some OSes, and defaults to the host's.
+    if crate["target"]:
+      project_crate["target"] = crate["target"]
+    if crate["is_proc_macro"]:
+      project_crate["proc_macro_dylib_path"] = os.path.normpath(
+          os.path.join(build_dir, crate["proc_macro_dylib_path"]))
+    project_crates.append(project_crate)
+
+  return {
+      "sysroot": sysroot,
+      "sysroot_src": os.path.join(sysroot, "lib", "rustlib", "src", "rust",
+                                  "library"),
+      "crates": project_crates,
+  }
+
+
+def main():
+  parser = argparse.ArgumentParser()
+  parser.add_argument("--crates",
+                      required=True,
+                      help="JSON file of the crates and aliases collected " +
+                      "from the GN metadata")
+  parser.add_argument("--output",
+                      required=True,
+                      help="Where to write the rust-project.json")
+  parser.add_argument("--sysroot",
+                      required=True,
+                      help="Rust sysroot, relative to the build dir")
+  parser.add_argument("--exclude-dir",
+                      action="append",
+                      default=[],
+                      help="GN directory whose crates are not listed")
+  args = parser.parse_args()
+
+  with open(args.crates, encoding="utf-8") as f:
+    metadata = json.load(f)
+  crates = [entry for entry in metadata if "root_module" in entry]
+  aliases = {
+      entry["label"]: entry["target"]
+      for entry in metadata if "root_module" not in entry
+  }
+
+  build_dir = os.getcwd()
+  sysroot = os.path.normpath(os.path.join(build_dir, args.sysroot))
+  project = generate_rust_project(crates, aliases, build_dir, sysroot,
+                                  args.exclude_dir)
+  with action_helpers.atomic_output(args.output, mode="w") as output:
+    json.dump(project, output, indent=2)
+    output.write("\n")
+  return 0
+
+
+if __name__ == "__main__":
+  sys.exit(main())
@@ -11,7 +11,8 @@
 # Code review processes must be applied to ensure that the build script
 # depends upon only these inputs:
 #
-# * The environment variables set by Cargo here:
+# * The environment variables set by Cargo here, except for CARGO, the
+#   CARGO_MAKEFLAGS jobserver and the DEP_* variables:
 #   https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts
 # * Output from rustc commands, e.g. to figure out the Rust version.
 #
@@ -19,12 +20,29 @@
 # is currently:
 #
 # * Generated .rs files
-# * cargo:rustc-cfg output.
+# * cargo:rustc-cfg and cargo:rustc-check-cfg output.
+# * cargo:rustc-env output, which is passed on to rustc_wrapper.py.
+# * cargo:rerun-if-changed output, which becomes a depfile for ninja. The
+#   paths must be within the source tree, or be in build_script_inputs.
+# * cargo:rustc-link-lib output for the libraries allowed by the crate's
+#   build_script_link_libs, and cargo:rustc-link-search output for OUT_DIR and
+#   the directories in its build_script_lib_dirs. The libraries are also
+#   written to a response file for the C++ linker.
+# * cargo:KEY=VALUE metadata, for crates with a `links` key. It is passed to
+#   the build scripts of the crates which depend on them, as DEP_* variables.
 #
-# That's it. We don't even support the other standard cargo:rustc-
-# output messages.
+# Both the `cargo::` directive syntax and the older `cargo:` one are accepted.
+# Other cargo:rustc- output messages, which we can not honor, fail the build
+# rather than being silently dropped.
+#
+# On Linux, the build script can be run in a sandbox (see `--sandbox`), which
+# enforces some of this: it can only read its crate's sources, its
+# build_script_inputs and the Rust toolchain, can only write to OUT_DIR, and has
+# no network access.
 
 import argparse
+import contextlib
+import ctypes
 import io
 import os
 import platform
@@ -50,6 +68,13 @@ def rustc_name():
     return "rustc"
 
 
+def rustdoc_name():
+  if platform.system() == 'Windows':
+    return "rustdoc.exe"
+  else:
+    return "rustdoc"
+
+
 def host_triple(rustc_path):
   """ Works out the host rustc target. """
   args = [rustc_path, "-vV"]
@@ -62,7 +87,482 @@ def host_triple(rustc_path):
   return known_vars["host"]
 
 
-RUSTC_CFG_LINE = re.compile("cargo:rustc-cfg=(.*)")
+RUSTC_PRINT_CFG_LINE = re.compile(r'(\w+)(?:="(.*)")?$')
+
+
+def opt_level(rustflags):
+  """Returns the optimization level which rustc uses given `rustflags`."""
+  level = "0"
+  rustflags = iter(rustflags)
+  for flag in rustflags:
+    if flag == "-O":
+      level = "2"
+    elif flag == "-C":
+      flag = "-C" + next(rustflags, "")
+    if flag.startswith("-Copt-level="):
+      level = flag[len("-Copt-level="):]
+  return level
+
+
+def cargo_cfg_env(rustc_path, target, rustflags):
+  """ Works out the CARGO_CFG_* environment variables which Cargo sets for
+  build scripts, from the cfgs that rustc reports for the target when given the
+  crate's `rustflags`. These are from the crate's configs too, such as target
+  features, `--cfg`s and the optimization level, which enables
+  debug_assertions at 0. """
+  args = [rustc_path, "--print", "cfg", "--target", target] + rustflags
+  output = subprocess.run(args,
+                          check=True,
+                          encoding="utf-8",
+                          stdout=subprocess.PIPE).stdout
+  cfgs = dict()
+  for line in output.splitlines():
+    m = RUSTC_PRINT_CFG_LINE.match(line.rstrip())
+    if not m:
+      continue
+    (name, value) = m.groups()
+    # As in Cargo, features are given by CARGO_FEATURE_* instead.
+    if name == "feature":
+      continue
+    values = cfgs.setdefault(name, [])
+    if value is not None:
+      values.append(value)
+  # Cfgs with several values, such as target_feature, are joined with commas,
+  # and cfgs without a value (such as unix) are set to an empty string.
+  return {
+      "CARGO_CFG_%s" % name.upper(): ",".join(values)
+      for (name, values) in cfgs.items()
+  }
+
+
+# A build script directive, in the `cargo::KEY=VALUE` syntax or the older
+# `cargo:KEY=VALUE` one. See
+# https://doc.rust-lang.org/cargo/reference/build-scripts.html#outputs-of-the-build-script
+CARGO_DIRECTIVE_LINE = re.compile("cargo:(:?)([^=]+)=(.*)")
+
+# Directives which only matter to Cargo itself, and can be ignored. The build
+# script is run with only the environment given on the command line, which
+# ninja already tracks, so `rerun-if-env-changed` has nothing to add. Variables
+# from the host environment, such as `CC`, are never set for it.
+IGNORED_DIRECTIVES = {"rerun-if-env-changed"}
+
+
+class Directives:
+  """The directives printed by a build script, see parse_directives()."""
+
+  def __init__(self):
+    # The rustc flags, one per line.
+    self.flags = ""
+    # The environment variables for rustc, one `K=V` per line.
+    self.rustenv = ""
+    self.rerun_paths = []
+    # The `(key, value)` pairs of metadata for dependents.
+    self.metadata = []
+    # The values of `rustc-link-lib` and `rustc-link-search` directives.
+    self.link_libs = []
+    self.link_search = []
+    self.warnings = []
+    # The directives which can not be honored.
+    self.errors = []
+
+
+def parse_directives(stdout):
+  """Parses the directives printed by a build script to its `stdout`, and
+  returns them as `Directives`."""
+  d = Directives()
+  for line in stdout.split("\n"):
+    m = CARGO_DIRECTIVE_LINE.match(line.rstrip())
+    if not m:
+      continue
+    new_syntax = m.group(1) == ":"
+    key = m.group(2)
+    value = m.group(3)
+    if key == "rustc-cfg":
+      d.flags = "%s--cfg\n%s\n" % (d.flags, value)
+    elif key == "rustc-check-cfg":
+      d.flags = "%s--check-cfg\n%s\n" % (d.flags, value)
+    elif key == "rustc-env":
+      if "=" in value:
+        d.rustenv = "%s%s\n" % (d.rustenv, value)
+      else:
+        d.errors.append("malformed directive: %s" % line.rstrip())
+    elif key == "rustc-link-lib":
+      d.link_libs.append(value)
+    elif key == "rustc-link-search":
+      d.link_search.append(value)
+    elif key == "rerun-if-changed":
+      d.rerun_paths.append(value)
+    elif key == "metadata" and new_syntax:
+      if "=" in value:
+        d.metadata.append(tuple(value.split("=", 1)))
+      else:
+        d.errors.append("malformed directive: %s" % line.rstrip())
+    elif key == "warning":
+      d.warnings.append(value)
+    elif key == "error":
+      d.errors.append(value)
+    elif key in IGNORED_DIRECTIVES:
+      pass
+    elif new_syntax or key.startswith("rustc-"):
+      d.errors.append("unsupported directive: %s" % line.rstrip())
+    else:
+      # This is `cargo:KEY=VALUE` metadata in the older syntax.
+      d.metadata.append((key, value))
+  if "--check-cfg" in d.flags:
+    # Once any cfg is declared, rustc checks them all. Declare those which
+    # Cargo always does, without restricting the feature names, which we only
+    # know when they are enabled.
+    d.flags += "--check-cfg\ncfg(docsrs, test)\n"
+    d.flags += "--check-cfg\ncfg(feature, values(any()))\n"
+  return d
+
+
+def dep_env_var_name(links, key):
+  """The name of the variable for the metadata `key` of a crate with the given
+  `links` key, in the build scripts of its dependents."""
+  return "DEP_%s_%s" % (links.upper().replace("-", "_"), key.upper().replace(
+      "-", "_"))
+
+
+def is_within(path, directory):
+  return os.path.commonpath([path, directory]) == directory
+
+
+def is_source_path(path, source_root):
+  """Whether `path` is in the source tree, and not in the build directory."""
+  return is_within(path, os.path.abspath(source_root)) and not is_within(
+      path, os.getcwd())
+
+
+# The kinds of `rustc-link-lib` libraries.
+LINK_LIB_KINDS = {"", "dylib", "framework", "static"}
+
+
+def parse_link_lib(link_lib):
+  """Parses a `rustc-link-lib` value, `[KIND[:MODIFIERS]=]NAME[:RENAME]`, into
+  its kind, its modifiers and the name of the library which is linked, which is
+  RENAME if it is given, as in rustc."""
+  (kind_and_modifiers, _, name) = link_lib.rpartition("=")
+  (kind, _, modifiers) = kind_and_modifiers.partition(":")
+  (name, _, rename) = name.partition(":")
+  return kind, modifiers.split(",") if modifiers else [], rename or name
+
+
+def linker_args(kind, modifiers, name, link_style):
+  """The arguments which make the C++ linker, of the given `link_style`, link
+  the library `name`, as rustc would."""
+  if kind == "static" and "-bundle" not in modifiers:
+    # rustc bundles the library into the rlib, which is given to the linker.
+    return []
+  if kind == "framework":
+    return ["-framework", name]
+  if link_style == "msvc":
+    return ["%s.lib" % name]
+  return ["-l%s" % name]
+
+
+def link_flags(link_libs, link_search, allowed_libs, allowed_lib_dirs,
+               build_script_out_dir, out_dir, src_dir, source_root,
+               link_style):
+  """Translates the `rustc-link-lib` and `rustc-link-search` directives of a
+  build script into rustc flags, one per line, and the arguments which make the
+  C++ linker link the same libraries, since it never sees the rustc flags.
+
+  The libraries must be in `allowed_libs`. The search paths, which are relative
+  to the crate's source directory as in Cargo, must be in `allowed_lib_dirs`,
+  which GN gives to the linker as `lib_dirs`, or be the build script's
+  `build_script_out_dir`, which stands for `out_dir` where its outputs are
+  copied.
+
+  Returns the flags, the linker arguments and a list of errors.
+  """
+  build_dir = os.getcwd()
+  flags = ""
+  link_args = []
+  errors = []
+  denied_libs = []
+  for link_lib in link_libs:
+    (kind, modifiers, name) = parse_link_lib(link_lib)
+    if kind not in LINK_LIB_KINDS:
+      errors.append("rustc-link-lib has an unknown kind: %s" % link_lib)
+    elif kind == "framework" and link_style != "apple":
+      errors.append("rustc-link-lib frameworks are only available on Apple "
+                    "platforms: %s" % link_lib)
+    elif name not in allowed_libs:
+      if name not in denied_libs:
+        denied_libs.append(name)
+    else:
+      flags += "-l\n%s\n" % link_lib
+      link_args += linker_args(kind, modifiers, name, link_style)
+  if denied_libs:
+    errors.append(
+        "rustc-link-lib libraries are not in build_script_link_libs: %s\n"
+        "If they should be linked, use this in its cargo_crate() target:\n"
+        "  build_script_link_libs = [ %s ]" %
+        (", ".join(denied_libs), ", ".join(
+            '"%s"' % lib for lib in list(allowed_libs) + denied_libs)))
+  allowed_lib_dirs = [os.path.abspath(d) for d in allowed_lib_dirs]
+  denied_dirs = []
+  # `[KIND=]PATH`
+  for search in link_search:
+    (kind, sep, path) = search.rpartition("=")
+    abs_path = os.path.abspath(os.path.join(src_dir, path))
+    if abs_path == os.path.abspath(build_script_out_dir):
+      abs_path = os.path.abspath(out_dir)
+    elif abs_path not in allowed_lib_dirs:
+      if not is_source_path(abs_path, source_root):
+        errors.append("rustc-link-search path is neither OUT_DIR nor in the "
+                      "source tree: %s" % path)
+      elif abs_path not in denied_dirs:
+        denied_dirs.append(abs_path)
+      continue
+    flags += "-L\n%s%s%s\n" % (kind, sep, os.path.relpath(abs_path, build_dir))
+  if denied_dirs:
+    source_root = os.path.abspath(source_root)
+    errors.append(
+        "rustc-link-search paths are not in build_script_lib_dirs: %s\n"
+        "If they should be searched, use this in its cargo_crate() target:\n"
+        "  build_script_lib_dirs = [ %s ]" %
+        (", ".join(denied_dirs), ", ".join(
+            '"//%s"' % os.path.relpath(d, source_root).replace(os.sep, "/")
+            for d in allowed_lib_dirs + denied_dirs)))
+  return flags, link_args, errors
+
+
+def rerun_if_changed_inputs(rerun_paths, src_dir, source_root, declared_inputs):
+  """Resolves the `rerun-if-changed` paths of a build script, which are relative
+  to the crate's source directory as in Cargo, into the files for its depfile.
+
+  A directory stands for itself and all the directories and files within it,
+  so that adding or removing a file, which changes the modification time of
+  its directory, reruns the build script as an edit does. The paths must be in
+  the source tree, outside of the build directory, unless they are one of the
+  `declared_inputs`, since ninja can not otherwise know when they are written.
+  Paths which do not exist are skipped, as build scripts often watch optional
+  files.
+
+  Returns the files, relative to the build directory, and lists of warnings
+  and errors.
+  """
+  build_dir = os.getcwd()
+  declared_inputs = {os.path.abspath(i) for i in declared_inputs}
+  inputs = []
+  warnings = []
+  errors = []
+  for rerun_path in rerun_paths:
+    path = os.path.abspath(os.path.join(src_dir, rerun_path))
+    if path not in declared_inputs and not is_source_path(path, source_root):
+      errors.append("rerun-if-changed path is neither in the source tree nor "
+                    "in build_script_inputs: %s" % rerun_path)
+    elif os.path.isdir(path):
+      for (dirpath, _, filenames) in os.walk(path):
+        inputs.append(dirpath)
+        inputs.extend(os.path.join(dirpath, f) for f in filenames)
+    elif os.path.exists(path):
+      inputs.append(path)
+    else:
+      warnings.append("rerun-if-changed path does not exist: %s" % rerun_path)
+  return [os.path.relpath(i, build_dir) for i in inputs], warnings, errors
+
+
+def compare_generated_files(out_dir, generated_files):
+  """Compares the files which a build script wrote to its `out_dir` with the
+  `generated_files` which it is declared to write, as paths relative to
+  `out_dir`.
+
+  Returns the files which were written but not declared, and those which were
+  declared but not written.
+  """
+  written = set()
+  for (dirpath, _, filenames) in os.walk(out_dir):
+    for filename in filenames:
+      path = os.path.relpath(os.path.join(dirpath, filename), out_dir)
+      written.add(path.replace(os.sep, "/"))
+  declared = set(os.path.normpath(f).replace(os.sep, "/")
+                 for f in generated_files)
+  return sorted(written - declared), sorted(declared - written)
+
+
+def build_script_outputs_error(crate_name, out_dir, undeclared, missing):
+  """Explains how `build_script_outputs` should be changed to match the files
+  which the build script of `crate_name` wrote to `out_dir`."""
+  lines = []
+  if undeclared:
+    lines.append("build script of crate %s wrote files to OUT_DIR which are "
+                 "not in build_script_outputs: %s" %
+                 (crate_name, ", ".join(undeclared)))
+  if missing:
+    lines.append("build script of crate %s did not write files in "
+                 "build_script_outputs: %s" % (crate_name, ", ".join(missing)))
+  written, _ = compare_generated_files(out_dir, [])
+  if written:
+    lines.append("In its cargo_crate() target, use:\n  build_script_outputs = "
+                 "[ %s ]" % ", ".join('"%s"' % f for f in written))
+  else:
+    lines.append("Remove build_script_outputs from its cargo_crate() target.")
+  return "\n".join(lines)
+
+
+# From <sched.h> and <sys/mount.h>.
+CLONE_NEWNS = 0x00020000
+CLONE_NEWUSER = 0x10000000
+CLONE_NEWNET = 0x40000000
+MS_RDONLY = 0x1
+MS_NOSUID = 0x2
+MS_NODEV = 0x4
+MS_NOEXEC = 0x8
+MS_REMOUNT = 0x20
+MS_NOATIME = 0x400
+MS_NODIRATIME = 0x800
+MS_BIND = 0x1000
+MS_REC = 0x4000
+MS_PRIVATE = 0x40000
+MS_RELATIME = 0x200000
+
+# The flags of an existing mount which are locked in a user namespace, and so
+# must be kept when remounting it, as given by statvfs().
+LOCKED_MOUNT_FLAGS = {
+    os.ST_NOSUID: MS_NOSUID,
+    os.ST_NODEV: MS_NODEV,
+    os.ST_NOEXEC: MS_NOEXEC,
+    os.ST_NOATIME: MS_NOATIME,
+    os.ST_NODIRATIME: MS_NODIRATIME,
+    os.ST_RELATIME: MS_RELATIME,
+} if platform.system() == 'Linux' else {}
+
+# What a build script needs from the system to run at all, such as the dynamic
+# loader and the C library, and the tools which rustc and cc-based build scripts
+# run. The loader also resolves `$ORIGIN` in the rpath of rustc through
+# /proc/self/exe.
+SANDBOX_SYSTEM_PATHS = [
+    "/bin",
+    "/etc/ld.so.cache",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/proc",
+    "/usr/bin",
+    "/usr/lib",
+    "/usr/lib32",
+    "/usr/lib64",
+]
+SANDBOX_DEVICES = ["/dev/null", "/dev/random", "/dev/urandom", "/dev/zero"]
+# An absolute path in the output of a build script.
+SANDBOX_PATH_RE = re.compile(r"/[^\s\"'`:,;()\[\]{}<>]+")
+
+
+class Sandbox:
+  """Runs a process in new Linux user, mount and network namespaces, where the
+  filesystem only holds the given paths, at the same locations as outside.
+
+  `enter` is meant to be the `preexec_fn` of the process. The new root is a
+  read-only tmpfs mounted on `root`, an empty directory, which is only visible
+  inside the sandbox. The empty directory `tmp_dir` is mounted as /tmp.
+  """
+
+  def __init__(self, root, tmp_dir, read_only_paths, writable_paths, cwd,
+               error_file):
+    self.root = root
+    self.tmp_dir = tmp_dir
+    self.read_only_paths = sorted(os.path.abspath(p) for p in read_only_paths)
+    self.writable_paths = sorted(os.path.abspath(p) for p in writable_paths)
+    self.cwd = os.path.abspath(cwd)
+    self.error_file = error_file
+    # The ids must be mapped from outside the user namespace.
+    self.uid = os.getuid()
+    self.gid = os.getgid()
+    self.libc = ctypes.CDLL(None, use_errno=True)
+
+  def _check(self, result, what):
+    if result != 0:
+      errno = ctypes.get_errno()
+      raise OSError(errno, "%s: %s" % (what, os.strerror(errno)))
+
+  def _mount(self, source, target, fstype, flags):
+    self._check(
+        self.libc.mount(
+            source.encode() if source else None, target.encode(),
+            fstype.encode() if fstype else None, ctypes.c_ulong(flags), None),
+        "mount %s" % target)
+
+  def _bind(self, path, writable, source=None):
+    """Makes `source`, which defaults to `path`, visible at `path` inside the
+    sandbox."""
+    source = source or path
+    if not os.path.lexists(source):
+      return
+    target = self.root + path
+    if os.path.islink(source):
+      # Keep the symlinks in system paths, such as /lib -> usr/lib.
+      os.makedirs(os.path.dirname(target), exist_ok=True)
+      if not os.path.lexists(target):
+        os.symlink(os.readlink(source), target)
+      return
+    if os.path.isdir(source):
+      os.makedirs(target, exist_ok=True)
+    else:
+      os.makedirs(os.path.dirname(target), exist_ok=True)
+      open(target, "a").close()
+    self._mount(source, target, None, MS_BIND | MS_REC)
+    flags = MS_REMOUNT | MS_BIND
+    statvfs_flags = os.statvfs(source).f_flag
+    for (st_flag, ms_flag) in LOCKED_MOUNT_FLAGS.items():
+      if statvfs_flags & st_flag:
+        flags |= ms_flag
+    if not writable:
+      flags |= MS_RDONLY
+    self._mount(None, target, None, flags)
+
+  def _is_covered(self, path, bound_paths):
+    return any(is_within(path, bound) for bound in bound_paths)
+
+  def undeclared_paths(self, output):
+    """Returns the absolute paths named in the `output` of the build script
+    which exist outside the sandbox but not inside it, as they are neither
+    system paths nor declared as sources or inputs."""
+    bound_paths = (SANDBOX_SYSTEM_PATHS + SANDBOX_DEVICES +
+                   self.read_only_paths + self.writable_paths + ["/tmp"])
+    paths = []
+    for match in SANDBOX_PATH_RE.finditer(output):
+      path = os.path.normpath(match.group(0))
+      if (path not in paths and os.path.exists(path)
+          and not self._is_covered(path, bound_paths)):
+        paths.append(path)
+    return paths
+
+  def enter(self):
+    try:
+      self._check(self.libc.unshare(CLONE_NEWUSER | CLONE_NEWNS | CLONE_NEWNET),
+                  "unshare")
+      with open("/proc/self/setgroups", "w") as f:
+        f.write("deny")
+      with open("/proc/self/uid_map", "w") as f:
+        f.write("%d %d 1" % (self.uid, self.uid))
+      with open("/proc/self/gid_map", "w") as f:
+        f.write("%d %d 1" % (self.gid, self.gid))
+      # Don't propagate the mounts below to the outside.
+      self._mount(None, "/", None, MS_REC | MS_PRIVATE)
+      self._mount("tmpfs", self.root, "tmpfs", MS_NOSUID | MS_NODEV)
+      bound_paths = []
+      for path in SANDBOX_SYSTEM_PATHS + self.read_only_paths:
+        # Paths within one which is already there are covered by it.
+        if not self._is_covered(path, bound_paths):
+          self._bind(path, writable=False)
+          bound_paths.append(path)
+      # Before the writable paths, which may be in /tmp.
+      self._bind("/tmp", writable=True, source=self.tmp_dir)
+      for path in SANDBOX_DEVICES + self.writable_paths:
+        self._bind(path, writable=True)
+      os.makedirs(self.root + self.cwd, exist_ok=True)
+      self._mount(None, self.root, None,
+                  MS_REMOUNT | MS_RDONLY | MS_NOSUID | MS_NODEV)
+      os.chroot(self.root)
+      os.chdir(self.cwd)
+    except Exception as e:
+      # The exception itself does not make it out of the preexec_fn.
+      with open(self.error_file, "w") as f:
+        f.write(str(e))
+      raise
 
 
 def main():
@@ -73,13 +573,76 @@ def main():
   parser.add_argument('--output',
                       required=True,
                       help='where to write output rustc flags')
+  parser.add_argument('--env-output',
+                      required=True,
+                      help='where to write output rustc environment variables')
+  parser.add_argument('--depfile',
+                      required=True,
+                      help='where to write the files the build script reads')
+  parser.add_argument('--source-root',
+                      required=True,
+                      help='root of the source tree')
+  parser.add_argument('--inputs',
+                      nargs='+',
+                      default=[],
+                      help='files declared as inputs of the build script')
+  parser.add_argument('--warnings-as-errors',
+                      action='store_true',
+                      help='fail when the build script prints warnings')
+  parser.add_argument('--allowed-link-libs',
+                      nargs='+',
+                      default=[],
+                      help='libraries which the build script may link with '
+                      'rustc-link-lib')
+  parser.add_argument('--allowed-lib-dirs',
+                      nargs='+',
+                      default=[],
+                      help='directories which the build script may search for '
+                      'libraries with rustc-link-search')
+  parser.add_argument('--link-args-output',
+                      help='where to write the arguments which make the linker '
+                      'link the libraries from rustc-link-lib')
+  parser.add_argument('--link-style',
+                      choices=['apple', 'gnu', 'msvc'],
+                      default='gnu',
+                      help='the syntax of the linker arguments')
+  parser.add_argument('--links',
+                      help='the `links` key of the crate, if it has one')
+  parser.add_argument('--metadata-output',
+                      help='where to write the metadata for dependents, for a '
+                      'crate with a `links` key')
+  parser.add_argument('--dep-metadata',
+                      action='append',
+                      default=[],
+                      help='metadata file of a dependency with a `links` '
+                      'key, may be repeated')
+  parser.add_argument('--sandbox',
+                      action='store_true',
+                      help='run the build script in a sandbox (Linux only)')
   parser.add_argument('--target', help='rust target triple')
   parser.add_argument('--features', help='features', nargs='+')
   parser.add_argument('--env', help='environment variable', nargs='+')
   parser.add_argument('--rust-prefix', required=True, help='rust path prefix')
-  parser.add_argument('--generated-files', nargs='+', help='any generated file')
+  parser.add_argument('--generated-files',
+                      nargs='+',
+                      default=[],
+                      help='any generated file')
+  parser.add_argument('--crate-name',
+                      required=True,
+                      help='name of the crate, for error messages')
   parser.add_argument('--out-dir', required=True, help='target out dir')
   parser.add_argument('--src-dir', required=True, help='target source dir')
+  parser.add_argument('--profile',
+                      required=True,
+                      choices=['debug', 'release'],
+                      help='Cargo profile matching the build configuration')
+  parser.add_argument('--debug',
+                      action='store_true',
+                      help='whether debug info is generated for the crate')
+  parser.add_argument('--rustflags-file',
+                      required=True,
+                      help='file listing the rustc flags of the crate, '
+                      'including those from its configs, one per line')
 
   args = parser.parse_args()
 
@@ -90,14 +653,16 @@ def main():
   # should generate. Mostly this is to ensure we can atomically
   # create those files, but it also serves to avoid side-effects
   # from the build script.
-  # In the future, we could consider isolating this build script
-  # into a chroot jail or similar on some platforms, but ultimately
-  # we are always going to be reliant on code review to ensure the
-  # build script is deterministic and trustworthy, so this would
-  # really just be a backup to humans.
-  with tempfile.TemporaryDirectory() as tempdir:
+  # With --sandbox, the build script is also isolated in Linux namespaces.
+  # Ultimately we are always going to be reliant on code review to ensure
+  # the build script is deterministic and trustworthy, so this is really
+  # just a backup to humans.
+  with contextlib.ExitStack() as stack:
+    tempdir = stack.enter_context(tempfile.TemporaryDirectory())
     env = {}  # try to avoid build scripts depending on other things
     env["RUSTC"] = os.path.abspath(rustc_path)
+    env["RUSTDOC"] = os.path.abspath(
+        os.path.join(args.rust_prefix, rustdoc_name()))
     env["OUT_DIR"] = tempdir
     env["CARGO_MANIFEST_DIR"] = os.path.abspath(args.src_dir)
     env["HOST"] = host_triple(rustc_path)
@@ -105,83 +670,174 @@ def main():
       env["TARGET"] = env["HOST"]
     else:
       env["TARGET"] = args.target
-    target_components = env["TARGET"].split("-")
-    if len(target_components) == 2:
-      env["CARGO_CFG_TARGET_ARCH"] = target_components[0]
-      env["CARGO_CFG_TARGET_VENDOR"] = ''
-      env["CARGO_CFG_TARGET_OS"] = target_components[1]
-      env["CARGO_CFG_TARGET_ENV"] = ''
-    elif len(target_components) == 3:
-      env["CARGO_CFG_TARGET_ARCH"] = target_components[0]
-      env["CARGO_CFG_TARGET_VENDOR"] = target_components[1]
-      env["CARGO_CFG_TARGET_OS"] = target_components[2]
-      env["CARGO_CFG_TARGET_ENV"] = ''
-    elif len(target_components) == 4:
-      env["CARGO_CFG_TARGET_ARCH"] = target_components[0]
-      env["CARGO_CFG_TARGET_VENDOR"] = target_components[1]
-      env["CARGO_CFG_TARGET_OS"] = target_components[2]
-      env["CARGO_CFG_TARGET_ENV"] = target_components[3]
-    else:
-      print(f'Invalid TARGET {env["TARGET"]}')
-      sys.exit(1)
-    # See https://crbug.com/325543500 for background.
-    # Cargo sets CARGO_CFG_TARGET_OS to "android" even when targeting *-androideabi.
-    if env["CARGO_CFG_TARGET_OS"].startswith("android"):
-      env["CARGO_CFG_TARGET_OS"] = "android"
-    elif env["CARGO_CFG_TARGET_OS"] == "darwin":
-      env["CARGO_CFG_TARGET_OS"] = "macos"
+    with open(args.rustflags_file, encoding="utf-8") as f:
+      rustflags = [line for line in f.read().splitlines() if line]
+    env.update(
+        cargo_cfg_env(rustc_path, env["TARGET"], rustflags))
     if args.features:
       for f in args.features:
         feature_name = f.upper().replace("-", "_")
         env["CARGO_FEATURE_%s" % feature_name] = "1"
     if args.env:
       for e in args.env:
-        (k, v) = e.split("=")
+        (k, v) = e.split("=", 1)
         env[k] = v
+    env["PROFILE"] = args.profile
+    env["OPT_LEVEL"] = opt_level(rustflags)
+    env["DEBUG"] = "true" if args.debug else "false"
+    # The build script is one of many actions run by the build system, so it
+    # should not spawn parallel jobs of its own.
+    env["NUM_JOBS"] = "1"
+    env["CARGO_ENCODED_RUSTFLAGS"] = "\x1f".join(rustflags)
+    if args.links:
+      env["CARGO_MANIFEST_LINKS"] = args.links
+    # The DEP_* variables from the dependencies with a `links` key.
+    for dep_metadata in args.dep_metadata:
+      if not os.path.isfile(dep_metadata):
+        print(f"ERROR: {dep_metadata} was not written by the build script of "
+              "a dependency in links_deps, which must have a `links` key",
+              file=sys.stderr)
+        return 1
+      with open(dep_metadata, encoding="utf-8") as f:
+        for line in f.read().splitlines():
+          (k, v) = line.split("=", 1)
+          env[k] = v
     # Pass through a couple which are useful for diagnostics
     if os.environ.get("RUST_BACKTRACE"):
       env["RUST_BACKTRACE"] = os.environ.get("RUST_BACKTRACE")
     if os.environ.get("RUST_LOG"):
       env["RUST_LOG"] = os.environ.get("RUST_LOG")
 
-    # In the future we should, set all the variables listed here:
-    # https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts
+    sandbox = None
+    if args.sandbox:
+      if platform.system() != 'Linux':
+        print("ERROR: build scripts can only be sandboxed on Linux",
+              file=sys.stderr)
+        return 1
+      sandbox_dir = stack.enter_context(tempfile.TemporaryDirectory())
+      sandbox_root = os.path.join(sandbox_dir, "root")
+      os.mkdir(sandbox_root)
+      sandbox_tmp = os.path.join(sandbox_dir, "tmp")
+      os.mkdir(sandbox_tmp)
+      sandbox = Sandbox(
+          sandbox_root,
+          sandbox_tmp,
+          read_only_paths=[
+              args.src_dir,
+              args.build_script,
+              # The Rust sysroot, for build scripts which run rustc.
+              os.path.dirname(os.path.abspath(args.rust_prefix)),
+          ] + args.inputs,
+          writable_paths=[tempdir],
+          cwd=args.src_dir,
+          error_file=os.path.join(sandbox_dir, "error"))
 
-    proc = subprocess.run([os.path.abspath(args.build_script)],
-                          env=env,
-                          cwd=args.src_dir,
-                          encoding='utf8',
-                          stdout=subprocess.PIPE,
-                          stderr=subprocess.PIPE)
+    try:
+      proc = subprocess.run([os.path.abspath(args.build_script)],
+                            env=env,
+                            cwd=args.src_dir,
+                            encoding='utf8',
+                            stdout=subprocess.PIPE,
+                            stderr=subprocess.PIPE,
+                            preexec_fn=sandbox.enter if sandbox else None)
+    except subprocess.SubprocessError:
+      if not sandbox:
+        raise
+      with open(sandbox.error_file) as f:
+        error = f.read()
+      print("ERROR: could not sandbox build script %s: %s\n"
+            "The sandbox needs unprivileged user namespaces. Set "
+            "`sandbox_rust_build_scripts = false` in args.gn to run build "
+            "scripts without it." % (args.build_script, error),
+            file=sys.stderr)
+      return 1
 
     if proc.stderr.rstrip():
       print(proc.stderr.rstrip(), file=sys.stderr)
+    if proc.returncode != 0 and sandbox:
+      print("ERROR: build script %s failed in its sandbox. It can only read "
+            "its crate's sources in %s, its build_script_inputs and the Rust "
+            "toolchain, and can only write to OUT_DIR. If it failed to read a "
+            "file, add the file to build_script_inputs." %
+            (args.build_script, args.src_dir),
+            file=sys.stderr)
+      for path in sandbox.undeclared_paths(proc.stdout + proc.stderr):
+        print("ERROR: build script %s used %s, which is not available in its "
+              "sandbox. Add it to build_script_inputs." %
+              (args.build_script, path),
+              file=sys.stderr)
     proc.check_returncode()
 
-    flags = ""
-    for line in proc.stdout.split("\n"):
-      m = RUSTC_CFG_LINE.match(line.rstrip())
-      if m:
-        flags = "%s--cfg\n%s\n" % (flags, m.group(1))
+    directives = parse_directives(proc.stdout)
+    errors = directives.errors
+    depfile_inputs, rerun_warnings, rerun_errors = rerun_if_changed_inputs(
+        directives.rerun_paths, args.src_dir, args.source_root, args.inputs)
+    errors += rerun_errors
+    warnings = directives.warnings + rerun_warnings
+    # Ninja shows what actions print to the build log, so warnings don't get
+    # lost, but it does not say which action printed them.
+    if args.warnings_as_errors:
+      errors += ["warning treated as error: %s" % w for w in warnings]
+    else:
+      for warning in warnings:
+        print("warning: build script of crate %s: %s" %
+              (args.crate_name, warning),
+              file=sys.stderr)
+    flags, link_args, link_errors = link_flags(
+        directives.link_libs, directives.link_search, args.allowed_link_libs,
+        args.allowed_lib_dirs, tempdir, args.out_dir, args.src_dir,
+        args.source_root, args.link_style)
+    flags = directives.flags + flags
+    errors += link_errors
+    if errors:
+      for error in errors:
+        print("ERROR: build script of crate %s (%s): %s" %
+              (args.crate_name, args.build_script, error),
+              file=sys.stderr)
+      return 1
 
     # AtomicOutput will ensure we only write to the file on disk if what we
     # give to write() is different than what's currently on disk.
     with action_helpers.atomic_output(args.output) as output:
       output.write(flags.encode("utf-8"))
+    # The environment variables are read by rustc_wrapper.py, one per line.
+    with action_helpers.atomic_output(args.env_output) as output:
+      output.write(directives.rustenv.encode("utf-8"))
+    # The linker arguments are a response file in the `ldflags` of dependents.
+    if args.link_args_output:
+      with action_helpers.atomic_output(args.link_args_output) as output:
+        output.write("".join("%s\n" % a for a in link_args).encode("utf-8"))
+    # As in Cargo, only crates with a `links` key pass metadata to dependents.
+    if args.metadata_output:
+      with action_helpers.atomic_output(args.metadata_output) as output:
+        for (key, value) in directives.metadata:
+          line = "%s=%s\n" % (dep_env_var_name(args.links, key), value)
+          output.write(line.encode("utf-8"))
+    # Rerun the build script when any file it asked Cargo to watch changes.
+    action_helpers.write_depfile(args.depfile, args.output, depfile_inputs)
+
+    # What the build script writes must match what it is declared to write,
+    # so that GN knows about each generated file, and no file which the crate
+    # may read is silently dropped.
+    undeclared, missing = compare_generated_files(tempdir, args.generated_files)
+    if undeclared or missing:
+      print("ERROR: %s" % build_script_outputs_error(args.crate_name, tempdir,
+                                                     undeclared, missing),
+            file=sys.stderr)
+      return 1
 
     # Copy any generated code out of the temporary directory,
     # atomically.
-    if args.generated_files:
-      for generated_file in args.generated_files:
-        in_path = os.path.join(tempdir, generated_file)
-        out_path = os.path.join(args.out_dir, generated_file)
-        out_dir = os.path.dirname(out_path)
-        if not os.path.exists(out_dir):
-          os.makedirs(out_dir)
-        with open(in_path, 'rb') as input:
-          with action_helpers.atomic_output(out_path) as output:
-            content = input.read()
-            output.write(content)
+    for generated_file in args.generated_files:
+      in_path = os.path.join(tempdir, generated_file)
+      out_path = os.path.join(args.out_dir, generated_file)
+      out_dir = os.path.dirname(out_path)
+      if not os.path.exists(out_dir):
+        os.makedirs(out_dir)
+      with open(in_path, 'rb') as input:
+        with action_helpers.atomic_output(out_path) as output:
+          content = input.read()
+          output.write(content)
 
 
 if __name__ == '__main__':
@@ -5,12 +5,14 @@
 # found in the LICENSE file.
 
 import argparse
+import json
 import pathlib
 import subprocess
 import shlex
 import os
 import sys
 import re
+import tempfile
 
 # Set up path to be able to import action_helpers.
 sys.path.append(
@@ -23,6 +25,7 @@ import action_helpers
 # * To remove dependencies on some environment variables from the .d file.
 # * To enable use of .rsp files.
 # * To work around two gn bugs on Windows
+# * To run Rust no-compile tests.
 #
 # LDFLAGS ESCAPING
 #
@@ -62,13 +65,83 @@ import action_helpers
 # On Windows platforms, this temporarily works around some issues in gn.
 # See comments inline, linking to the relevant gn fixes.
 #
+# NOCOMPILE TESTS
+#
+# With --chromium-nocompile-test=<path>, the path is the crate root of a
+# no-compile test, as defined by rust_nocompile_test()
+# in //build/rust/rust_nocompile_test.gni. rustc is then expected to fail,
+# reporting exactly the errors annotated in the crate root with
+# `//~ ERROR <text>` (or `//~^ ERROR <text>` to refer to the line above). If
+# it does, an empty output file and a depfile are written in place of what
+# rustc would have produced, so that ninja considers the test up to date.
+#
+# JSON DIAGNOSTICS
+#
+# With --json-diagnostics, rustc reports its warnings and errors as JSON. They
+# are written as a JSON list to a .diagnostics.json file next to the depfile,
+# for tools which collect them, and their human-readable rendering is printed
+# as rustc would have.
+#
+# CLIPPY
+#
+# With --chromium-clippy, clippy-driver from the Rust toolchain is run in place
+# of rustc, to lint a first-party crate as
+# defined by rust_target() in //build/rust/rust_target.gni. Only the lint pass
+# is run, without codegen, and an empty output file is written in place of
+# what rustc would have produced, so that ninja considers the target up to
+# date.
+#
+# RUSTDOC
+#
+# With --chromium-rustdoc=<dir>, rustdoc from the Rust toolchain is run in
+# place of rustc, to generate the docs of a crate into the directory, as
+# defined by rust_doc() in //build/rust/rust_doc.gni. Each crate has its own
+# directory, and items from its deps link to the directories of their docs,
+# which are listed by crate name in the file given by --chromium-rustdoc-deps.
+# rustdoc writes the depfile, which is checked as rustc's would be. With
+# --chromium-doctest=<crate type> instead, rustdoc compiles and runs the
+# doctests of the crate, as the given crate type. rustdoc can not list the
+# files which it reads then, so the depfile lists the crate's sources and the
+# build flags which it may read. In both cases, an empty output file is
+# written in place of what rustc would have produced, so that ninja considers
+# the target up to date.
+#
+# RUSTFLAGS
+#
+# With --chromium-print-rustflags, the crate is not compiled. Instead, the
+# flags which GN gives rustc from the crate's `rustflags` and those of its
+# configs are written in place of the library, one per line, as defined by
+# cargo_crate() in //build/rust/cargo_crate.gni. They are passed on to the
+# crate's build script, as Cargo would. The target is left out, as Cargo gives
+# it separately.
+#
+# UNUSED SOURCES
+#
+# Every file read by rustc must be listed in the GN `sources` or `inputs`,
+# except for the build flags which the `chromium::buildflag!` macro reads, as
+# listed in the file named by the CHROMIUM_BUILDFLAGS_FILE variable. With
+# --chromium-strict-sources=<file>, which first-party crates give, the reverse
+# is also checked: every file listed in `sources` must be read by rustc, so
+# that stale entries are removed. The file lists the `sources`, as the rsp file
+# also holds the `inputs`. Third-party crates list the union of their sources
+# for all configurations, so they do not give it.
+#
 # Usage:
-#   rustc_wrapper.py --rustc <path to rustc> --depfile <path to .d file>
+#   rustc_wrapper.py [--json-diagnostics] --rustc <path to rustc>
+#      --depfile <path to .d file>
 #      -- <normal rustc args> LDFLAGS {{ldflags}} RUSTENV {{rustenv}}
+# The rustc args may include the --chromium-* flags of this script, which GN
+# templates put in the `rustflags` of a single target, as GN has no other way
+# to give the tool arguments per target. They are removed before running
+# rustc, and are not part of the crate's environment as RUSTENV entries
+# would be.
 # The LDFLAGS token is discarded, and everything after that is converted
 # to being a series of -Clink-arg=X arguments, until or unless RUSTENV
 # is encountered, after which those are interpreted as environment
 # variables to pass to rustc (and which will be removed from the .d file).
+# A RUSTENV entry of the form @<path> names a file of such environment
+# variables, one per line, as written by run_build_script.py for the
+# cargo:rustc-env output of build scripts.
 #
 # Both LDFLAGS and RUSTENV **MUST** be specified, in that order, even if
 # the list following them is empty.
@@ -83,6 +156,7 @@ import action_helpers
 # script.
 
 FILE_RE = re.compile("[^:]+: (.+)")
+NOCOMPILE_EXPECTATION_RE = re.compile(r"//~(\^*)\s*ERROR\s+(.+?)\s*$")
 
 
 # Equivalent of python3.9 built-in
@@ -92,16 +166,10 @@ def remove_lib_suffix_from_l_args(text):
   return text
 
 
-def verify_inputs(depline, sources, abs_build_root):
-  """Verify everything used by rustc (found in `depline`) was specified in the
-  GN build rule (found in `sources` or `inputs`).
-
-  TODO(danakj): This allows things in `sources` that were not actually used by
-  rustc since third-party packages sources need to be a union of all build
-  configs/platforms for simplicity in generating build rules. For first-party
-  code we could be more strict and reject things in `sources` that were not
-  consumed.
-  """
+def depline_files(depline, abs_build_root):
+  """Returns the files that rustc says are needed in `depline`, as a dict from
+  their path relative to the build dir, as GN writes it, to the path written
+  by rustc."""
 
   # str.removeprefix() does not exist before python 3.9.
   def remove_prefix(text, prefix):
@@ -113,12 +181,22 @@ def verify_inputs(depline, sources, abs_build_root):
     return os.path.relpath(os.path.normpath(remove_prefix(
         p, abs_build_root))).replace('\\', '/')
 
-  # Collect the files that rustc says are needed.
-  found_files = {}
   m = FILE_RE.match(depline)
-  if m:
-    files = m.group(1)
-    found_files = {normalize_path(f): f for f in files.split()}
+  if not m:
+    return {}
+  return {normalize_path(f): f for f in m.group(1).split()}
+
+
+def verify_inputs(depline, sources, abs_build_root):
+  """Verify everything used by rustc (found in `depline`) was specified in the
+  GN build rule (found in `sources` or `inputs`).
+
+  This allows things in `sources` that were not actually used by rustc since
+  third-party packages sources need to be a union of all build
+  configs/platforms for simplicity in generating build rules. First-party code
+  is also checked by `verify_sources_used()`.
+  """
+  found_files = depline_files(depline, abs_build_root)
   # Get which ones are not listed in GN.
   missing_files = found_files.keys() - sources
 
@@ -136,17 +214,250 @@ def verify_inputs(depline, sources, abs_build_root):
   return False
 
 
+def verify_sources_used(deplines, sources, abs_build_root):
+  """Verify everything specified in the GN `sources` of a first-party crate
+  was used by rustc (found in `deplines`)."""
+  found_files = set()
+  for depline in deplines:
+    found_files.update(depline_files(depline, abs_build_root))
+  unused_files = sources - found_files
+  for f in sorted(unused_files):
+    print(f'ERROR: file in GN sources is not used by rustc: {f}',
+          file=sys.stderr)
+  if unused_files:
+    print('Remove it from the sources of the GN target, or set '
+          '`allow_unused_sources = true` if it is only compiled for some cfgs.',
+          file=sys.stderr)
+    return False
+  return True
+
+
+def expand_rustenv_files(rustenv):
+  """Replaces each `@<path>` entry in `rustenv` with the environment variables
+  listed in that file, one per line."""
+  expanded = []
+  for item in rustenv:
+    if item.startswith("@"):
+      with open(item[1:], encoding="utf-8") as f:
+        expanded.extend(line for line in f.read().splitlines() if line)
+    else:
+      expanded.append(item)
+  return expanded
+
+
+def write_json_diagnostics(stderr, path, echo):
+  """Writes the JSON diagnostics in rustc's `stderr` to `path`, as a list. If
+  `echo` is set, their human-readable rendering is printed, along with any
+  output which is not a diagnostic (such as an ICE backtrace)."""
+  diagnostics = []
+  for line in stderr.splitlines():
+    try:
+      diagnostic = json.loads(line)
+    except ValueError:
+      if echo:
+        print(line, file=sys.stderr)
+      continue
+    # Other messages, such as future-incompat reports and artifact
+    # notifications, are not diagnostics of the crate.
+    if diagnostic.get("$message_type") != "diagnostic":
+      continue
+    diagnostics.append(diagnostic)
+    if echo and diagnostic.get("rendered"):
+      print(diagnostic["rendered"], end="", file=sys.stderr)
+  # The mtime must be updated, as the file is an output of the build step.
+  with action_helpers.atomic_output(path, mode="w",
+                                    only_if_changed=False) as output:
+    json.dump(diagnostics, output)
+
+
+def rustdoc_args(rustc_args, doc_dir, dep_doc_dirs, doctest_crate_type):
+  """Returns the arguments for running rustdoc on the crate compiled by
+  `rustc_args`, to generate its docs in `doc_dir`, linking to the docs of the
+  crates in `dep_doc_dirs`, or, if `doctest_crate_type` is set, to run its
+  doctests."""
+  args = []
+  rustc_args = iter(rustc_args)
+  for arg in rustc_args:
+    if arg.startswith("--emit="):
+      # The docs, and the files read to generate them, replace the library.
+      dep_info = [
+          emit for emit in arg[len("--emit="):].split(",")
+          if emit.startswith("dep-info")
+      ]
+      if not doctest_crate_type:
+        args.append("--emit=" + ",".join(
+            ["toolchain-shared-resources", "invocation-specific"] + dep_info))
+      continue
+    if arg == "-o":
+      next(rustc_args)
+      continue
+    args.append(arg)
+    if arg == "--crate-name":
+      crate_name = next(rustc_args)
+      args.append(crate_name)
+    elif arg == "--crate-type":
+      crate_type = next(rustc_args)
+      args.append(doctest_crate_type or crate_type)
+
+  if doctest_crate_type:
+    return args + ["--test"]
+
+  args += ["-o", doc_dir, "-Zunstable-options"]
+  for dep_crate_name, dep_doc_dir in sorted(dep_doc_dirs.items()):
+    if dep_crate_name == crate_name:
+      continue
+    # rustdoc resolves relative URLs from the directory of the crate's docs.
+    url = os.path.relpath(dep_doc_dir, os.path.join(doc_dir, crate_name))
+    args += [
+        "--extern-html-root-url", f"{dep_crate_name}={url.replace(os.sep, '/')}"
+    ]
+  return args
+
+
+def buildflag_files(buildflags_file):
+  """Returns the files listing the build flags which the `chromium::buildflag!`
+  macro may read, including `buildflags_file` which lists the others."""
+  files = {buildflags_file}
+  with open(buildflags_file, encoding="utf-8") as f:
+    files.update(line for line in f.read().splitlines() if line)
+  return files
+
+
+def gn_rustflags(rustc_args):
+  """Returns the flags in `rustc_args` which came from the GN `rustflags` of
+  the crate and its configs, other than the target."""
+  rustflags = []
+  # The {{rustflags}} follow the crate type, and end with the flags added by
+  # the toolchain. On some platforms, {{rustdeps}} and {{externs}} come first.
+  args = iter(rustc_args[rustc_args.index("--crate-type") + 2:])
+  for arg in args:
+    if arg.startswith("--emit="):
+      break
+    if arg in ("--target", "--extern", "-L", "-l"):
+      next(args, None)
+    elif not arg.startswith(("--target=", "--extern=", "-L", "-l")):
+      rustflags.append(arg)
+  return rustflags
+
+
+def parse_nocompile_expectations(source):
+  """Returns a list of [line, text] expected errors annotated in `source`."""
+  expectations = []
+  with open(source, encoding="utf-8") as f:
+    for line_number, line in enumerate(f, start=1):
+      m = NOCOMPILE_EXPECTATION_RE.search(line)
+      if m:
+        expectations.append([line_number - len(m.group(1)), m.group(2)])
+  return expectations
+
+
+def nocompile_error_lines(diagnostic, source):
+  """Returns the lines in `source` which an error `diagnostic` points at. Spans
+  inside macro expansions are followed back to the macro invocation."""
+  lines = set()
+  for span in diagnostic["spans"]:
+    if not span["is_primary"]:
+      continue
+    while span:
+      if os.path.normpath(span["file_name"]) == os.path.normpath(source):
+        lines.add(span["line_start"])
+        break
+      span = span["expansion"] and span["expansion"]["span"]
+  return lines
+
+
+def verify_nocompile_test(returncode, stderr, source):
+  """Verify that rustc failed with the errors expected by the no-compile test
+  in `source`, as reported in its JSON diagnostics in `stderr`."""
+  if returncode == 0:
+    print(f'ERROR: {source} compiled, but is a no-compile test',
+          file=sys.stderr)
+    return False
+
+  errors = []
+  for line in stderr.splitlines():
+    try:
+      diagnostic = json.loads(line)
+    except ValueError:
+      # Anything which is not a diagnostic (such as an ICE backtrace) is passed
+      # through for debugging.
+      print(line, file=sys.stderr)
+      continue
+    # Errors without a location only summarize the other errors.
+    if diagnostic.get("level") == "error" and diagnostic["spans"]:
+      errors.append(diagnostic)
+
+  expectations = parse_nocompile_expectations(source)
+  if not expectations:
+    print(f'ERROR: {source} has no `//~ ERROR` expectations', file=sys.stderr)
+    return False
+
+  ok = True
+  matched = set()
+  for line, text in expectations:
+    found = False
+    for i, error in enumerate(errors):
+      if (line in nocompile_error_lines(error, source)
+          and text in error["message"]):
+        matched.add(i)
+        found = True
+    if not found:
+      print(f'ERROR: {source}:{line}: expected error not found: {text}',
+            file=sys.stderr)
+      ok = False
+  for i, error in enumerate(errors):
+    if i not in matched:
+      rendered = error["rendered"].rstrip()
+      print(f'ERROR: {source}: unexpected error:\n{rendered}', file=sys.stderr)
+      ok = False
+  return ok
+
+
+def rustc_output(rustc_args):
+  """Returns the path of the output which rustc is asked to produce."""
+  return rustc_args[rustc_args.index("-o") + 1]
+
+
 def main():
   parser = argparse.ArgumentParser()
   parser.add_argument('--rustc', required=True, type=pathlib.Path)
   parser.add_argument('--depfile', required=True, type=pathlib.Path)
   parser.add_argument('--rsp', type=pathlib.Path, required=True)
   parser.add_argument('--target-windows', action='store_true')
+  parser.add_argument('--json-diagnostics', action='store_true')
   parser.add_argument('-v', action='store_true')
   parser.add_argument('args', metavar='ARG', nargs='+')
 
   args = parser.parse_args()
 
+  # The flags which GN gives a single target in its `rustflags`.
+  target_parser = argparse.ArgumentParser(prog="rustc_wrapper.py rustflags")
+  target_parser.add_argument('--chromium-clippy',
+                             action='store_true',
+                             help='lint the crate with clippy-driver')
+  target_parser.add_argument('--chromium-nocompile-test',
+                             metavar='SOURCE',
+                             help='expect the crate root, a no-compile test, '
+                             'to fail with the errors annotated in it')
+  target_parser.add_argument('--chromium-print-rustflags',
+                             action='store_true',
+                             help='write the rustflags in place of the crate')
+  target_parser.add_argument('--chromium-strict-sources',
+                             metavar='FILE',
+                             help='check that rustc reads every source listed '
+                             'in FILE')
+  target_parser.add_argument('--chromium-rustdoc',
+                             metavar='DIR',
+                             help='generate the docs of the crate in DIR')
+  target_parser.add_argument('--chromium-rustdoc-deps',
+                             metavar='FILE',
+                             help='file listing the doc dirs of the deps, as '
+                             'CRATE_NAME=DIR lines')
+  target_parser.add_argument('--chromium-doctest',
+                             metavar='CRATE_TYPE',
+                             help='run the doctests of the crate, compiled as '
+                             'CRATE_TYPE')
+
   remaining_args = args.args
 
   ldflags_separator = remaining_args.index("LDFLAGS")
@@ -158,12 +469,28 @@ def main():
   except:
     sources_separator = None
   rustc_args = remaining_args[:ldflags_separator]
+  target_args = target_parser.parse_args(
+      [arg for arg in rustc_args if arg.startswith("--chromium-")])
+  rustc_args = [arg for arg in rustc_args if not arg.startswith("--chromium-")]
   ldflags = remaining_args[ldflags_separator + 1:rustenv_separator]
   rustenv = remaining_args[rustenv_separator + 1:sources_separator]
 
   abs_build_root = os.getcwd().replace('\\', '/') + '/'
   is_windows = sys.platform == 'win32' or args.target_windows
 
+  if target_args.chromium_print_rustflags:
+    output = rustc_output(rustc_args)
+    # Stand in for the outputs which rustc would have produced.
+    with action_helpers.atomic_output(output, mode="w",
+                                      only_if_changed=False) as f:
+      f.write("".join(f"{arg}\n" for arg in gn_rustflags(rustc_args)))
+    with action_helpers.atomic_output(args.depfile) as depfile:
+      depfile.write(f"{output}:\n".encode("utf-8"))
+    if args.json_diagnostics:
+      write_json_diagnostics("", args.depfile.with_suffix(".diagnostics.json"),
+                             echo=False)
+    return 0
+
   rustc_args.extend(["-Clink-arg=%s" % arg for arg in ldflags])
 
   with open(args.rsp) as rspfile:
@@ -187,38 +514,140 @@ def main():
 
   env = os.environ.copy()
   fixed_env_vars = []
-  for item in rustenv:
+  buildflags_file = None
+  for item in expand_rustenv_files(rustenv):
     (k, v) = item.split("=", 1)
     env[k] = v
     fixed_env_vars.append(k)
+    if k == "CHROMIUM_BUILDFLAGS_FILE":
+      buildflags_file = v
+
+  rustc = args.rustc
+  nocompile_source = target_args.chromium_nocompile_test
+  clippy = target_args.chromium_clippy
+  temp_dir = None
+  if clippy:
+    # clippy-driver is a drop-in replacement for rustc.
+    rustc = rustc.with_name("clippy-driver" + rustc.suffix)
+    temp_dir = tempfile.TemporaryDirectory()
+    # Emit the crate metadata, which needs no codegen, instead of linking.
+    metadata = os.path.join(temp_dir.name, "clippy.rmeta")
+    rustc_args = [
+        arg.replace(",link", f",metadata={metadata}")
+        if arg.startswith("--emit=") else arg for arg in rustc_args
+    ]
+    # These are read by clippy-driver, but are not set by GN.
+    fixed_env_vars += ["CLIPPY_ARGS", "CLIPPY_CONF_DIR"]
+  doc_dir = target_args.chromium_rustdoc
+  doctest_crate_type = target_args.chromium_doctest
+  rustdoc = doc_dir or doctest_crate_type
+  if rustdoc:
+    rustc = rustc.with_name("rustdoc" + rustc.suffix)
+    output = rustc_output(rustc_args)
+    dep_doc_dirs = {}
+    if target_args.chromium_rustdoc_deps:
+      with open(target_args.chromium_rustdoc_deps, encoding="utf-8") as f:
+        dep_doc_dirs = dict(
+            line.split("=", 1) for line in f.read().splitlines() if line)
+    rustc_args = rustdoc_args(rustc_args, doc_dir, dep_doc_dirs,
+                              doctest_crate_type)
+
+  capture_stderr = nocompile_source or args.json_diagnostics
+  if capture_stderr:
+    rustc_args.append("--error-format=json")
+    if args.json_diagnostics and "--color=always" in rustc_args:
+      rustc_args.append("--json=diagnostic-rendered-ansi")
 
   try:
     if args.v:
-      print(' '.join(f'{k}={shlex.quote(v)}' for k, v in env.items()),
-            args.rustc, shlex.join(rustc_args))
-    r = subprocess.run([args.rustc, *rustc_args], env=env, check=False)
+      print(' '.join(f'{k}={shlex.quote(v)}' for k, v in env.items()), rustc,
+            shlex.join(rustc_args))
+    # The doctest results are only printed if some fail.
+    r = subprocess.run([rustc, *rustc_args],
+                       env=env,
+                       check=False,
+                       stdout=subprocess.PIPE if doctest_crate_type else None,
+                       stderr=subprocess.PIPE if capture_stderr else None,
+                       text=True)
   finally:
+    if temp_dir:
+      temp_dir.cleanup()
     if not args.v:
       os.remove(out_rsp)
+
+  if args.json_diagnostics:
+    # No-compile tests print the diagnostics which they did not expect.
+    write_json_diagnostics(r.stderr,
+                           args.depfile.with_suffix(".diagnostics.json"),
+                           echo=not nocompile_source)
+
+  if nocompile_source:
+    if not verify_nocompile_test(r.returncode, r.stderr, nocompile_source):
+      return 1
+    # Stand in for the outputs which rustc did not produce.
+    output = rustc_output(rustc_args)
+    pathlib.Path(output).write_bytes(b"")
+    with action_helpers.atomic_output(args.depfile) as depfile:
+      depfile.write(f"{output}: {nocompile_source}\n".encode("utf-8"))
+    return 0
+
   if r.returncode != 0:
+    if doctest_crate_type:
+      print(r.stdout, end="")
     sys.exit(r.returncode)
 
+  if rustdoc:
+    # Stand in for the output which rustdoc did not produce.
+    pathlib.Path(output).write_bytes(b"")
+  if doctest_crate_type:
+    files = set(sources)
+    if buildflags_file:
+      files.update(buildflag_files(buildflags_file))
+    with action_helpers.atomic_output(args.depfile) as depfile:
+      depfile.write(f"{output}: {' '.join(sorted(files))}\n".encode("utf-8"))
+    return 0
+
+  if clippy:
+    # Stand in for the output which clippy-driver did not produce.
+    output = rustc_output(rustc_args)
+    pathlib.Path(output).write_bytes(b"")
+
   final_depfile_lines = []
   dirty = False
   with open(args.depfile, encoding="utf-8") as d:
     # Figure out which lines we want to keep in the depfile. If it's not the
     # whole file, we will rewrite the file.
-    env_dep_re = re.compile("# env-dep:(.*)=.*")
+    # The value is missing for variables which are not set.
+    env_dep_re = re.compile("# env-dep:([^=\n]*)")
     for line in d:
+      if clippy:
+        # Name the placeholder output rather than the temporary metadata.
+        line = line.replace(metadata, output)
+        dirty = True
+      elif rustdoc and line.startswith(f"{args.depfile}:"):
+        # rustdoc names the depfile itself, rather than the placeholder.
+        line = f"{output}:" + line[len(f"{args.depfile}:"):]
+        dirty = True
       m = env_dep_re.match(line)
       if m and m.group(1) in fixed_env_vars:
         dirty = True  # We want to skip this line.
       else:
         final_depfile_lines.append(line)
 
-  # Verify each dependent file is listed in sources/inputs.
+  # Verify each dependent file is listed in sources/inputs. The build flags of
+  # the deps are found by GN from their metadata, so they can not be listed.
+  allowed_files = set(sources)
+  if buildflags_file:
+    allowed_files.update(buildflag_files(buildflags_file))
   for line in final_depfile_lines:
-    if not verify_inputs(line, sources, abs_build_root):
+    if not verify_inputs(line, allowed_files, abs_build_root):
+      return 1
+  strict_sources = target_args.chromium_strict_sources
+  if strict_sources:
+    with open(strict_sources, encoding="utf-8") as f:
+      gn_sources = set(line for line in f.read().splitlines() if line)
+    if not verify_sources_used(final_depfile_lines, gn_sources,
+                               abs_build_root):
       return 1
 
   if dirty:  # we made a change, let's write out the file
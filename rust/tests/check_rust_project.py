@@ -0,0 +1,113 @@
+#!/usr/bin/env python3
+
+# Copyright 2023 The Chromium Authors
+# Use of this source code is governed by a BSD-style license that can be
+# found in the LICENSE file.
+
+# Checks the rust-project.json generated for the crates in //build/rust/tests
+# by //build/rust/rust_project.gni.
+
+import argparse
+import json
+import os
+import sys
+
+
+def find_crate(project, display_name):
+  for crate in project["crates"]:
+    if crate["display_name"] == display_name:
+      return crate
+  raise AssertionError(f"{display_name} is missing")
+
+
+def dep_crate(project, crate, name):
+  for dep in crate["deps"]:
+    if dep["name"] == name:
+      return project["crates"][dep["crate"]]
+  raise AssertionError(f"{crate['display_name']} has no dep named {name}")
+
+
+def check(project, expect_prelude):
+  assert os.path.isabs(project["sysroot"])
+  assert project["sysroot_src"].startswith(project["sysroot"])
+  for crate in project["crates"]:
+    assert os.path.isabs(crate["root_module"]), crate["root_module"]
+    assert os.path.exists(crate["root_module"]), crate["root_module"]
+    # The stdlib is loaded by rust-analyzer from the sysroot instead.
+    assert not crate["display_name"].startswith("//build/rust/std:")
+    assert not crate["display_name"].startswith("//build/rust/std/")
+
+  # Features, cfgs from rustflags and from the build script, and rustenv.
+  target1 = find_crate(project, "//build/rust/tests/test_rlib_crate:target1")
+  assert target1["edition"] == "2021"
+  for cfg in [
+      'feature="my-feature_a"',
+      'feature="my-feature_b"',
+      "test_a_and_b",
+      "build_script_ran",
+  ]:
+    assert cfg in target1["cfg"], cfg
+  assert target1["env"]["ENV_VAR_FOR_BUILD_SCRIPT"] == "42"
+  assert target1["env"]["BUILD_SCRIPT_VERSION_STRING"] == \
+      "test_rlib_crate key=value"
+  out_dir = target1["env"]["OUT_DIR"]
+  assert os.path.isabs(out_dir)
+  assert os.path.exists(os.path.join(out_dir, "generated", "generated.rs"))
+  assert out_dir in target1["source"]["include_dirs"]
+
+  target2 = find_crate(project, "//build/rust/tests/test_rlib_crate:target2")
+  assert target2["root_module"] == target1["root_module"]
+  assert 'feature="my-feature_b"' not in target2["cfg"]
+
+  # The crate of the bin depends on target1 through its crate name.
+  bin_crate = find_crate(
+      project,
+      "//build/rust/tests/test_rlib_crate:test_rlib_crate_associated_bin")
+  assert dep_crate(project, bin_crate, "test_rlib_crate") == target1
+
+  # Aliased deps are named by their alias.
+  aliased = find_crate(project,
+                       "//build/rust/tests/test_aliased_deps:test_aliased_deps")
+  real_name = dep_crate(project, aliased, "other_name")
+  assert real_name["display_name"] == \
+      "//build/rust/tests/test_aliased_deps:real_name"
+
+  if expect_prelude:
+    # `chromium::import!` needs the `chromium` crate and its proc macro, and
+    # the GN deps of the crate.
+    buildflags = find_crate(
+        project, "//build/rust/tests/test_buildflags:test_buildflags")
+    assert buildflags["is_workspace_member"]
+    chromium = dep_crate(project, buildflags, "chromium")
+    import_attribute = dep_crate(project, chromium, "import_attribute")
+    assert import_attribute["is_proc_macro"]
+    # The path of the dylib is that of the rust_macro tool's output.
+    dylib_path = import_attribute["proc_macro_dylib_path"]
+    assert os.path.isabs(dylib_path), dylib_path
+    assert os.path.exists(dylib_path), dylib_path
+    for var in ["CHROMIUM_GN_DEPS_FILE", "CHROMIUM_BUILDFLAGS_FILE"]:
+      assert os.path.exists(buildflags["env"][var]), var
+    assert buildflags["env"]["CHROMIUM_GN_DIR"] == \
+        "//build/rust/tests/test_buildflags"
+
+
+def main():
+  parser = argparse.ArgumentParser()
+  parser.add_argument("--rust-project",
+                      required=True,
+                      help="The generated rust-project.json")
+  parser.add_argument("--expect-prelude",
+                      action="store_true",
+                      help="Whether the chromium prelude is enabled")
+  parser.add_argument("--stamp", required=True, help="Touched on success")
+  args = parser.parse_args()
+
+  with open(args.rust_project, encoding="utf-8") as f:
+    check(json.load(f), args.expect_prelude)
+  with open(args.stamp, "w"):
+    pass
+  return 0
+
+
+if __name__ == "__main__":
+  sys.exit(main())
@@ -0,0 +1,19 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+pub const SOME_STRING: &str = chromium::buildflag!(SOME_STRING);
+
+pub fn is_enabled() -> bool {
+    chromium::buildflag!(IS_ENABLED)
+}
+
+#[cfg(test)]
+#[test]
+fn test_buildflag_values() {
+    assert!(is_enabled());
+    assert!(!chromium::buildflag!(IS_DISABLED));
+    assert!(chromium::buildflag!(IS_PARENTHESIZED));
+    assert_eq!(chromium::buildflag!(SOME_NUMBER), 42i64);
+    assert_eq!(SOME_STRING, "hello \"goat\"");
+}
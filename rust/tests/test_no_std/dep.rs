@@ -0,0 +1,9 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+#![no_std]
+
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
@@ -0,0 +1,20 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The test harness requires the stdlib.
+#![cfg_attr(not(test), no_std)]
+
+chromium::import! {
+    ":test_no_std_dep" as dep;
+}
+
+pub fn add_twice(a: u32, b: u32) -> u32 {
+    dep::add(dep::add(a, b), b)
+}
+
+#[cfg(test)]
+#[test]
+fn test_add_twice() {
+    assert_eq!(add_twice(1, 2), 5);
+}
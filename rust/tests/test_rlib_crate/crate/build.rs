@@ -10,6 +10,37 @@ use std::str::{self, FromStr};
 
 fn main() {
     println!("cargo:rustc-cfg=build_script_ran");
+    // Test that the `cargo::` directive syntax is understood, along with the
+    // older `cargo:` one.
+    println!("cargo::rustc-cfg=new_directive_syntax");
+    println!(
+        "cargo::rustc-check-cfg=cfg(build_script_ran, new_directive_syntax, is_new_rustc, \
+         is_old_rustc, is_android, is_mac, has_feature_a, has_feature_b, test_a_and_b)"
+    );
+    // Test that environment variables from build scripts are passed to rustc.
+    // The value may contain `=`, as only the first one ends the name.
+    println!("cargo:rustc-env=BUILD_SCRIPT_VERSION_STRING=test_rlib_crate key=value");
+    // Test that the files the build script reads are tracked in its depfile,
+    // including all of the files in a directory.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo::rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=ENV_VAR_FOR_BUILD_SCRIPT");
+    // Test that metadata is passed from crates with a `links` key to the build
+    // scripts of their dependents, in both directive syntaxes.
+    if let Ok(links) = env::var("CARGO_MANIFEST_LINKS") {
+        assert_eq!(links, "test_rlib");
+        println!("cargo:include-dir=some/path");
+        println!("cargo::metadata=version=1.2");
+        // Test that a system library allowed by `build_script_link_libs` can be
+        // linked. See test_sys_crate for one built by the build script.
+        if env::var("CARGO_CFG_TARGET_OS").unwrap() == "linux" {
+            println!("cargo:rustc-link-lib=m");
+        }
+    }
+    if env::var_os("EXPECT_DEP_METADATA").is_some() {
+        assert_eq!(env::var("DEP_TEST_RLIB_INCLUDE_DIR").unwrap(), "some/path");
+        assert_eq!(env::var("DEP_TEST_RLIB_VERSION").unwrap(), "1.2");
+    }
     let minor = match rustc_minor_version() {
         Some(minor) => minor,
         None => return,
@@ -53,10 +84,55 @@ fn main() {
     // Confirm the following env var is set, but do not attempt to validate content
     // since the whole point is that it will differ on different platforms.
     env::var_os("CARGO_CFG_TARGET_ARCH").unwrap();
+    env::var_os("CARGO_CFG_TARGET_FAMILY").unwrap();
+    env::var_os("CARGO_CFG_TARGET_HAS_ATOMIC").unwrap();
+    // These come from rustc's cfgs for the target, so only take known values.
+    let pointer_width = env::var("CARGO_CFG_TARGET_POINTER_WIDTH").unwrap();
+    assert!(["16", "32", "64"].contains(&pointer_width.as_str()), "{pointer_width}");
+    let endian = env::var("CARGO_CFG_TARGET_ENDIAN").unwrap();
+    assert!(endian == "little" || endian == "big", "{endian}");
+    // Features are only given by CARGO_FEATURE_*.
+    assert!(env::var_os("CARGO_CFG_FEATURE").is_none());
+    check_cargo_env();
 
     generate_some_code().unwrap();
 }
 
+// Checks the rest of the environment that Cargo sets for build scripts, from
+// the `cargo_pkg_*` fields and the build configuration.
+fn check_cargo_env() {
+    let var = |name| env::var(name).unwrap_or_else(|_| panic!("{name} is not set"));
+    assert_eq!(var("CARGO_PKG_NAME"), "test_rlib_crate");
+    assert_eq!(var("CARGO_PKG_AUTHORS"), "The Chromium Authors");
+    assert_eq!(var("CARGO_PKG_DESCRIPTION"), "A crate to test cargo_crate()");
+    assert_eq!(var("CARGO_PKG_VERSION"), "0.2.7-beta.1+build.5");
+    assert_eq!(var("CARGO_PKG_VERSION_MAJOR"), "0");
+    assert_eq!(var("CARGO_PKG_VERSION_MINOR"), "2");
+    assert_eq!(var("CARGO_PKG_VERSION_PATCH"), "7");
+    assert_eq!(var("CARGO_PKG_VERSION_PRE"), "beta.1");
+    assert_eq!(var("CARGO_PKG_LICENSE"), "BSD-3-Clause");
+    assert_eq!(var("CARGO_PKG_REPOSITORY"), "https://chromium.googlesource.com/chromium/src/build");
+    assert_eq!(var("CARGO_PKG_RUST_VERSION"), "1.70");
+    // Missing optional fields are set, but empty.
+    assert_eq!(var("CARGO_PKG_HOMEPAGE"), "");
+    assert_eq!(var("CARGO_PKG_LICENSE_FILE"), "");
+    assert_eq!(var("CARGO_PKG_README"), "");
+    assert!(var("CARGO_MANIFEST_PATH").ends_with("Cargo.toml"));
+    assert!(Path::new(&var("RUSTDOC")).exists());
+
+    let profile = var("PROFILE");
+    assert!(profile == "debug" || profile == "release", "{profile}");
+    let opt_level = var("OPT_LEVEL");
+    assert!(["0", "1", "2", "3", "s", "z"].contains(&opt_level.as_str()), "{opt_level}");
+    assert_eq!(profile == "debug", opt_level == "0");
+    let debug = var("DEBUG");
+    assert!(debug == "true" || debug == "false", "{debug}");
+    assert!(var("NUM_JOBS").parse::<u32>().unwrap() > 0);
+    // Only target1 has rustflags, which are separated by 0x1f.
+    let rustflags = var("CARGO_ENCODED_RUSTFLAGS");
+    assert!(rustflags.is_empty() || rustflags == "--cfg\x1ftest_a_and_b", "{rustflags:?}");
+}
+
 fn generate_some_code() -> std::io::Result<()> {
     let output_dir = Path::new(&env::var_os("OUT_DIR").unwrap()).join("generated");
     let _ = std::fs::create_dir_all(&output_dir);
@@ -6,6 +6,9 @@ include!(concat!(env!("OUT_DIR"), "/generated/generated.rs"));
 
 pub fn say_hello_from_crate() {
     assert_eq!(run_some_generated_code(), 42);
+    assert_eq!(env!("BUILD_SCRIPT_VERSION_STRING"), "test_rlib_crate key=value");
+    #[cfg(not(new_directive_syntax))]
+    panic!("Wasn't passed a cfg in the cargo:: directive syntax");
     #[cfg(is_new_rustc)]
     println!("Is new rustc!");
     #[cfg(is_old_rustc)]
@@ -53,4 +56,26 @@ mod tests {
     fn test_generated_code_works() {
         assert_eq!(crate::run_some_generated_code(), 42);
     }
+
+    #[test]
+    fn test_new_directive_syntax_works() {
+        #[cfg(not(new_directive_syntax))]
+        panic!("Wasn't passed a cfg in the cargo:: directive syntax");
+    }
+
+    #[test]
+    fn test_build_script_env_works() {
+        assert_eq!(env!("BUILD_SCRIPT_VERSION_STRING"), "test_rlib_crate key=value");
+    }
+
+    #[test]
+    fn test_cargo_pkg_env_works() {
+        assert_eq!(env!("CARGO_CRATE_NAME"), "test_rlib_crate");
+        assert_eq!(env!("CARGO_PKG_VERSION_MAJOR"), "0");
+        assert_eq!(env!("CARGO_PKG_VERSION_MINOR"), "2");
+        assert_eq!(env!("CARGO_PKG_VERSION_PATCH"), "7");
+        assert_eq!(env!("CARGO_PKG_VERSION_PRE"), "beta.1");
+        assert_eq!(env!("CARGO_PKG_LICENSE"), "BSD-3-Clause");
+        assert_eq!(env!("CARGO_PKG_HOMEPAGE"), "");
+    }
 }
@@ -5,6 +5,12 @@
 // Demo library to ensure that serde_json_lenient is working independently of
 // its integration with Chromium.
 
+// Imported by its versioned GN path, to check that the path resolves to the
+// epoch in `deps`.
+chromium::import! {
+    "//third_party/rust/serde_json_lenient/v0_1:lib";
+}
+
 use serde_json_lenient::{Result, Value};
 
 #[cxx::bridge]
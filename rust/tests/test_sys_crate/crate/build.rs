@@ -0,0 +1,68 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// The symbols which the native library defines.
+const SYMBOLS: &[&str] = &["test_sys_native_add"];
+
+fn main() {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let object = Path::new(&out_dir).join("native.o");
+    let status = Command::new(env::var("RUSTC").unwrap())
+        .args(["--crate-type=lib", "--emit=obj", "-Cpanic=abort", "-Copt-level=2"])
+        .arg(format!("--target={}", env::var("TARGET").unwrap()))
+        .arg("-o")
+        .arg(&object)
+        .arg("native/native.rs")
+        .status()
+        .unwrap();
+    assert!(status.success());
+    write_archive(&Path::new(&out_dir).join("libtest_sys_native.a"), &fs::read(&object).unwrap());
+    fs::remove_file(&object).unwrap();
+
+    // Test that a library in OUT_DIR can be linked, as `-sys` crates do.
+    println!("cargo:rerun-if-changed=native");
+    println!("cargo:rustc-link-search=native={out_dir}");
+    println!("cargo:rustc-link-lib=static=test_sys_native");
+}
+
+// Writes a static library holding the `object`, in the GNU ar format, as there
+// is no C toolchain to run `ar`.
+fn write_archive(path: &Path, object: &[u8]) {
+    let mut symbol_names = Vec::new();
+    for symbol in SYMBOLS {
+        symbol_names.extend_from_slice(symbol.as_bytes());
+        symbol_names.push(0);
+    }
+    let symbol_table_size = 4 + 4 * SYMBOLS.len() + symbol_names.len();
+    // The offset of the object's header, after the magic and the symbol table.
+    let object_offset = 8 + 60 + symbol_table_size + symbol_table_size % 2;
+
+    let mut archive = b"!<arch>\n".to_vec();
+    archive.extend(member_header("/", symbol_table_size));
+    archive.extend((SYMBOLS.len() as u32).to_be_bytes());
+    for _ in SYMBOLS {
+        archive.extend((object_offset as u32).to_be_bytes());
+    }
+    archive.extend(symbol_names);
+    if symbol_table_size % 2 == 1 {
+        archive.push(b'\n');
+    }
+    archive.extend(member_header("native.o/", object.len()));
+    archive.extend_from_slice(object);
+    if object.len() % 2 == 1 {
+        archive.push(b'\n');
+    }
+    fs::write(path, archive).unwrap();
+}
+
+fn member_header(name: &str, size: usize) -> Vec<u8> {
+    let header = format!("{name:<16}{:<12}{:<6}{:<6}{:<8}{size:<10}`\n", 0, 0, 0, 644);
+    assert_eq!(header.len(), 60);
+    header.into_bytes()
+}
@@ -0,0 +1,12 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+// The native library which the build script builds, as a C library would be.
+
+#![no_std]
+
+#[no_mangle]
+pub extern "C" fn test_sys_native_add(a: u32, b: u32) -> u32 {
+    a.wrapping_add(b)
+}
@@ -0,0 +1,20 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+extern "C" {
+    fn test_sys_native_add(a: u32, b: u32) -> u32;
+}
+
+pub fn add(a: u32, b: u32) -> u32 {
+    // SAFETY: The function from the native library has no preconditions.
+    unsafe { test_sys_native_add(a, b) }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_native_library_is_linked() {
+        assert_eq!(super::add(2, 3), 5);
+    }
+}
@@ -0,0 +1,10 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+pub fn add_one(a: u32) -> u32 {
+    a + 1
+}
+
+#[cfg(test)]
+mod tests;
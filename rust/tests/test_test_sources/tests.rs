@@ -0,0 +1,10 @@
+// Copyright 2023 The Chromium Authors
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+use super::add_one;
+
+#[test]
+fn test_add_one() {
+    assert_eq!(add_one(1), 2);
+}
@@ -20,16 +20,21 @@ import shlex
 
 
 class Options:
-  def __init__(self, output, rulename, header_guard, flags):
+  def __init__(self, output, rust_output, rulename, header_guard, flags,
+               rust_flags):
     self.output = output
+    self.rust_output = rust_output
     self.rulename = rulename
     self.header_guard = header_guard
     self.flags = flags
+    self.rust_flags = rust_flags
 
 
 def GetOptions():
   parser = optparse.OptionParser()
   parser.add_option('--output', help="Output header name inside --gen-dir.")
+  parser.add_option('--rust-output',
+                    help="Output file for the flags as Rust constants, if any.")
   parser.add_option('--rulename',
                     help="Helpful name of build rule for including in the " +
                          "comment at the top of the file.")
@@ -59,11 +64,16 @@ def GetOptions():
   # Everything after --flags are flags. true/false are remapped to 1/0,
   # everything else is passed through.
   flags = []
+  rust_flags = []
   for flag in defs[flags_index + 1 :]:
     equals_index = flag.index('=')
     key = flag[:equals_index]
     value = flag[equals_index + 1:]
 
+    # Rust can tell booleans apart from integers, so look at the value before
+    # it is canonicalized.
+    rust_flags.append((key, RustConstant(value)))
+
     # Canonicalize and validate the value.
     if value == 'true':
       value = '1'
@@ -72,9 +82,30 @@ def GetOptions():
     flags.append((key, str(value)))
 
   return Options(output=output,
+                 rust_output=cmdline_options.rust_output,
                  rulename=cmdline_options.rulename,
                  header_guard=header_guard,
-                 flags=flags)
+                 flags=flags,
+                 rust_flags=rust_flags)
+
+
+# Strings with any escapes other than these may not mean the same thing in C++
+# and Rust, so they are not made available to Rust.
+RUST_STRING_RE = re.compile(r'"(?:[^"\\]|\\["\\nt])*"')
+
+
+def RustConstant(value):
+  """Returns the (type, literal) of the Rust constant for a flag value, or None
+  if the value has no Rust equivalent (such as a C++ expression)."""
+  if value in ('true', '(true)'):
+    return ('bool', 'true')
+  if value in ('false', '(false)'):
+    return ('bool', 'false')
+  if re.fullmatch(r'-?[0-9]+', value):
+    return ('i64', value)
+  if RUST_STRING_RE.fullmatch(value):
+    return ('&str', value)
+  return None
 
 
 def WriteHeader(options):
@@ -93,5 +124,22 @@ def WriteHeader(options):
     output_file.write('\n#endif  // %s\n' % options.header_guard)
 
 
+def WriteRust(options):
+  # This file is read by the `chromium::buildflag!` macro, which expects one
+  # `pub const` item per line, and can also be used with `include!`.
+  with open(options.rust_output, 'w') as output_file:
+    output_file.write("// Generated by build/write_buildflag_header.py\n")
+    if options.rulename:
+      output_file.write('// From "' + options.rulename + '"\n')
+    output_file.write('\n')
+    for key, constant in options.rust_flags:
+      if constant is None:
+        output_file.write('// %s is not representable in Rust.\n' % key)
+      else:
+        output_file.write('pub const %s: %s = %s;\n' % (key, *constant))
+
+
 options = GetOptions()
 WriteHeader(options)
+if options.rust_output:
+  WriteRust(options)